@@ -0,0 +1,341 @@
+use std::io;
+
+use crate::checkpoint::invalid;
+use crate::zlib;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+// deflate compresses at most 258 bytes into 2 bits
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+// A picture in shades of gray, from 0 (black) to 255 (white)
+pub struct Bitmap {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    // Whether the data look like a netpbm or a PNG picture, rather than text
+    pub fn recognize(data: &[u8]) -> bool {
+        data.starts_with(&PNG_SIGNATURE)
+            || matches!(data, [b'P', b'1'..=b'6', c, ..] if c.is_ascii_whitespace())
+    }
+
+    // Read a netpbm (PBM, PGM or PPM, plain or raw) or a non-interlaced PNG picture
+    pub fn read(data: &[u8]) -> io::Result<Self> {
+        if data.starts_with(&PNG_SIGNATURE) {
+            Bitmap::read_png(data)
+        } else {
+            Bitmap::read_netpbm(data)
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.pixels[row * self.width + col]
+    }
+
+    fn read_netpbm(data: &[u8]) -> io::Result<Self> {
+        let mut position = 0;
+        let magic = token(data, &mut position);
+        let kind = match magic {
+            Some(&[b'P', kind]) if (b'1'..=b'6').contains(&kind) => kind - b'0',
+            _ => return Err(invalid("not a netpbm picture".to_string())),
+        };
+        let width = number(data, &mut position)?;
+        let height = number(data, &mut position)?;
+        let max = if kind == 1 || kind == 4 {
+            1
+        } else {
+            number(data, &mut position)?
+        };
+        if max == 0 || max > 65535 {
+            return Err(invalid("invalid header of a netpbm picture".to_string()));
+        }
+        let channels = if kind == 3 || kind == 6 { 3 } else { 1 };
+        // every sample takes at least a bit of the data
+        let count = check_size(width, height, width.checked_mul(channels), 8 * data.len())?;
+        // the samples scaled to 0..=255, in PBM 1 is black
+        let scale = |sample: usize| match kind {
+            1 | 4 => 255 * (1 - sample.min(1)) as u8,
+            _ => (sample.min(max) * 255 / max) as u8,
+        };
+
+        let mut samples = Vec::with_capacity(count);
+        match kind {
+            1 => {
+                // the bits of a plain PBM need not be separated by whitespace
+                while samples.len() < count {
+                    match data.get(position) {
+                        Some(b'#') => skip_comment(data, &mut position),
+                        Some(&c @ (b'0' | b'1')) => {
+                            samples.push(scale((c - b'0') as usize));
+                            position += 1;
+                        }
+                        Some(c) if c.is_ascii_whitespace() => position += 1,
+                        _ => return Err(invalid("invalid pixels of a PBM picture".to_string())),
+                    }
+                }
+            }
+            2 | 3 => {
+                for _ in 0..count {
+                    samples.push(scale(number(data, &mut position)?));
+                }
+            }
+            _ => {
+                // a single whitespace character separates the header from the raster
+                let raster = data.get(position + 1..).unwrap_or_default();
+                if kind == 4 {
+                    let stride = width.div_ceil(8);
+                    for row in raster.chunks(stride).take(height) {
+                        for col in 0..width {
+                            let bit = row.get(col / 8).map_or(0, |b| b >> (7 - col % 8) & 1);
+                            samples.push(scale(bit as usize));
+                        }
+                    }
+                } else if max < 256 {
+                    samples.extend(raster.iter().take(count).map(|&s| scale(s as usize)));
+                } else {
+                    let pairs = raster.chunks_exact(2).take(count);
+                    samples.extend(pairs.map(|s| scale(256 * s[0] as usize + s[1] as usize)));
+                }
+                if samples.len() < count {
+                    return Err(invalid("unexpected end of a netpbm picture".to_string()));
+                }
+            }
+        }
+        let pixels = samples.chunks(channels).map(luminance).collect();
+        Ok(Bitmap {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn read_png(data: &[u8]) -> io::Result<Self> {
+        let mut chunks = Vec::new();
+        let mut position = PNG_SIGNATURE.len();
+        // the chunks up to IEND, which must all be there
+        let truncated = || invalid("unexpected end of a PNG picture".to_string());
+        loop {
+            let length = match data.get(position..position + 4) {
+                Some(&[a, b, c, d]) => u32::from_be_bytes([a, b, c, d]) as usize,
+                _ => return Err(truncated()),
+            };
+            let end = position + 8 + length;
+            if end + 4 > data.len() {
+                return Err(truncated());
+            }
+            let crc = &data[end..end + 4];
+            if zlib::crc32(&data[position + 4..end]).to_be_bytes() != crc {
+                return Err(invalid("wrong checksum of a PNG chunk".to_string()));
+            }
+            let kind = &data[position + 4..position + 8];
+            if kind == b"IEND" {
+                break;
+            }
+            chunks.push((kind, &data[position + 8..end]));
+            position = end + 4;
+        }
+
+        let header = match chunks.first() {
+            Some(&(b"IHDR", header)) if header.len() == 13 => header,
+            _ => return Err(invalid("no header of a PNG picture".to_string())),
+        };
+        let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let (depth, color) = (header[8] as usize, header[9]);
+        if header[12] != 0 {
+            return Err(invalid(
+                "interlaced PNG pictures are not supported".to_string(),
+            ));
+        }
+        let channels = match (color, depth) {
+            (0, 1) | (0, 2) | (0, 4) | (3, 1) | (3, 2) | (3, 4) | (3, 8) => 1,
+            (0, _) | (2, _) | (4, _) | (6, _) if depth == 8 || depth == 16 => {
+                [1, 0, 3, 0, 2, 0, 4][color as usize]
+            }
+            _ => return Err(invalid("invalid header of a PNG picture".to_string())),
+        };
+
+        let compressed = chunks
+            .iter()
+            .filter(|&&(kind, _)| kind == b"IDAT")
+            .flat_map(|&(_, data)| data.iter().cloned())
+            .collect::<Vec<_>>();
+        let bits = channels * depth;
+        let limit = MAX_DEFLATE_RATIO * compressed.len();
+        let stride = (width * bits).div_ceil(8);
+        // the scanlines, every one of them preceded by its filter type
+        let size = check_size(width, height, Some(stride + 1), limit)?;
+        let filtered = zlib::decompress(&compressed, size)?;
+        if filtered.len() < size {
+            return Err(invalid("unexpected end of a PNG picture".to_string()));
+        }
+        let scanlines = unfilter(&filtered, height, stride, bits.div_ceil(8))?;
+
+        // the palette, with the transparency of its colors
+        let palette = chunks
+            .iter()
+            .find(|&&(kind, _)| kind == b"PLTE")
+            .map_or(&[][..], |&(_, data)| data);
+        let alpha = chunks
+            .iter()
+            .find(|&&(kind, _)| kind == b"tRNS")
+            .map_or(&[][..], |&(_, data)| data);
+        let indexed = |index: usize| -> io::Result<u8> {
+            let rgb = palette
+                .get(3 * index..3 * index + 3)
+                .ok_or_else(|| invalid("a color out of the palette".to_string()))?;
+            let a = *alpha.get(index).unwrap_or(&255);
+            Ok(luminance(&[rgb[0], rgb[1], rgb[2], a]))
+        };
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in scanlines.chunks(stride) {
+            for col in 0..width {
+                let pixel = if depth < 8 {
+                    let byte = row[col * depth / 8];
+                    let sample = (byte >> (8 - depth - col * depth % 8)) & ((1 << depth) - 1);
+                    if color == 3 {
+                        indexed(sample as usize)?
+                    } else {
+                        sample * (255 / ((1 << depth) - 1))
+                    }
+                } else {
+                    // the most significant bytes of the samples
+                    let bytes = depth / 8;
+                    let samples = row[col * channels * bytes..(col + 1) * channels * bytes]
+                        .iter()
+                        .step_by(bytes)
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    if color == 3 {
+                        indexed(samples[0] as usize)?
+                    } else {
+                        luminance(&samples)
+                    }
+                };
+                pixels.push(pixel);
+            }
+        }
+        Ok(Bitmap {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+// The size of a picture of rows of the given length (in samples or in bytes), checked
+// before it is allocated: a picture without pixels or larger than the limit is refused
+fn check_size(width: usize, height: usize, row: Option<usize>, limit: usize) -> io::Result<usize> {
+    match row.and_then(|row| row.checked_mul(height)) {
+        Some(size) if width > 0 && height > 0 && size <= limit => Ok(size),
+        _ => Err(invalid(format!(
+            "invalid size of a picture: {} x {} pixels",
+            width, height
+        ))),
+    }
+}
+
+// The shade of gray of a pixel given by its gray or red, green and blue samples,
+// optionally followed by its opacity, put onto a white background
+fn luminance(samples: &[u8]) -> u8 {
+    let rgb = |r, g, b| (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+    let (gray, alpha) = match *samples {
+        [gray] => (gray as u32, 255),
+        [gray, alpha] => (gray as u32, alpha as u32),
+        [r, g, b] => (rgb(r, g, b), 255),
+        [r, g, b, alpha, ..] => (rgb(r, g, b), alpha as u32),
+        [] => (255, 255),
+    };
+    ((gray * alpha + 255 * (255 - alpha)) / 255) as u8
+}
+
+// Reverse the filters of the PNG scanlines, every one of them preceded by its filter type
+fn unfilter(data: &[u8], height: usize, stride: usize, step: usize) -> io::Result<Vec<u8>> {
+    let mut scanlines = vec![0u8; height * stride];
+    for row in 0..height {
+        let filter = data[row * (stride + 1)];
+        let line = &data[row * (stride + 1) + 1..(row + 1) * (stride + 1)];
+        let (previous, current) = scanlines.split_at_mut(row * stride);
+        let above = previous
+            .get((row.max(1) - 1) * stride..)
+            .filter(|_| row > 0);
+        let current = &mut current[..stride];
+        for i in 0..stride {
+            let a = if i >= step { current[i - step] } else { 0 };
+            let b = above.map_or(0, |above| above[i]);
+            let c = if i >= step {
+                above.map_or(0, |above| above[i - step])
+            } else {
+                0
+            };
+            current[i] = line[i].wrapping_add(match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(invalid("invalid filter of a PNG scanline".to_string())),
+            });
+        }
+    }
+    Ok(scanlines)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn number(data: &[u8], position: &mut usize) -> io::Result<usize> {
+    token(data, position)
+        .and_then(|t| std::str::from_utf8(t).ok()?.parse().ok())
+        .ok_or_else(|| invalid("invalid number in a netpbm picture".to_string()))
+}
+
+// The next word of a netpbm header, skipping whitespace and comments
+fn token<'a>(data: &'a [u8], position: &mut usize) -> Option<&'a [u8]> {
+    loop {
+        match data.get(*position) {
+            Some(b'#') => skip_comment(data, position),
+            Some(c) if c.is_ascii_whitespace() => *position += 1,
+            Some(_) => break,
+            None => return None,
+        }
+    }
+    let start = *position;
+    while data
+        .get(*position)
+        .is_some_and(|c| !c.is_ascii_whitespace())
+    {
+        *position += 1;
+    }
+    Some(&data[start..*position])
+}
+
+fn skip_comment(data: &[u8], position: &mut usize) {
+    while data.get(*position).is_some_and(|&c| c != b'\n') {
+        *position += 1;
+    }
+}
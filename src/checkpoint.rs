@@ -0,0 +1,72 @@
+use std::io::{self, BufRead, Write};
+
+// A position in the sequential search, from which it can be resumed
+// `rows` and `branches` form a stack: on every level of the search tree, the row
+// that was chosen and its index among the rows of the column being covered
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Checkpoint {
+    pub count: u64,      // #solutions found before reaching this position
+    pub exhausted: bool, // has the whole search tree been searched through?
+    pub rows: Vec<u32>,
+    pub branches: Vec<u32>,
+}
+
+impl Checkpoint {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "count {}", self.count)?;
+        write!(writer, "rows")?;
+        for r in self.rows.iter() {
+            write!(writer, " {}", r)?;
+        }
+        write!(writer, "\nbranches")?;
+        for b in self.branches.iter() {
+            write!(writer, " {}", b)?;
+        }
+        writeln!(writer)?;
+        if self.exhausted {
+            writeln!(writer, "exhausted")?;
+        }
+        Ok(())
+    }
+
+    pub fn load(reader: impl BufRead) -> io::Result<Self> {
+        let mut checkpoint = Checkpoint::new();
+        for line in reader.lines() {
+            let line = line?;
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("count") => checkpoint.count = parse_numbers(words)?.pop().unwrap_or(0),
+                Some("rows") => checkpoint.rows = parse_numbers(words)?,
+                Some("branches") => checkpoint.branches = parse_numbers(words)?,
+                Some("exhausted") => checkpoint.exhausted = true,
+                Some(w) => return Err(invalid(format!("unknown checkpoint entry `{}`", w))),
+                None => (),
+            }
+        }
+        if checkpoint.rows.len() != checkpoint.branches.len() {
+            return Err(invalid(
+                "checkpoint rows and branches differ in length".to_string(),
+            ));
+        }
+        Ok(checkpoint)
+    }
+}
+
+fn parse_numbers<'a, T: std::str::FromStr>(
+    words: impl Iterator<Item = &'a str>,
+) -> io::Result<Vec<T>> {
+    words
+        .map(|w| {
+            w.parse()
+                .map_err(|_| invalid(format!("invalid number `{}` in checkpoint", w)))
+        })
+        .collect()
+}
+
+pub(crate) fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,32 +1,44 @@
+use crate::bitmap::Bitmap;
 use crate::matrix::Matrix;
 use crate::tiles::Point;
 
+// The label of the pixels of the images not loaded from text
+pub const FILLED: char = 'x';
+
 pub struct Image {
     data: Matrix<Option<usize>>,
     points: Vec<Point>,
+    labels: Vec<char>, // the characters of the filled pixels in the input
     width: usize,
 }
 
 impl Image {
     pub fn load(reader: impl std::io::BufRead, filled: char) -> Self {
+        Image::load_labelled(reader, &[filled])
+    }
+
+    // Load the image with any of the `filled` characters as a filled pixel, labelled by it
+    pub fn load_labelled(reader: impl std::io::BufRead, filled: &[char]) -> Self {
         let mut points = Vec::new();
+        let mut labels = Vec::new();
         let mut data = Matrix::new(255, 255);
         let mut width = 0;
         for (line, x) in reader
             .lines()
-            .filter_map(Result::ok)
+            .map_while(Result::ok)
             .take_while(|s| !s.is_empty())
             .zip(0..=255)
         // iterate with x coordinate
         {
             data.add_row();
-            for (_, y) in line
+            for (c, y) in line
                 .chars()
                 .zip(0..=255) // iterate with y coordinate
-                .filter(|(c, _)| *c == filled)
+                .filter(|(c, _)| filled.contains(c))
             {
                 let id = points.len();
                 points.push(Point { x, y });
+                labels.push(c);
                 data[(x as usize, y as usize)] = Some(id);
             }
             if line.len() > width {
@@ -36,10 +48,110 @@ impl Image {
         Image {
             data,
             points,
+            labels,
+            width,
+        }
+    }
+
+    // The image with the given pixels filled
+    // Like with the text, only the pixels within the first 255 x 255 ones are taken
+    pub fn from_cells(cells: impl IntoIterator<Item = Point>) -> Self {
+        let mut cells = cells
+            .into_iter()
+            .filter(|p| (0..255).contains(&p.x) && (0..255).contains(&p.y))
+            .collect::<Vec<_>>();
+        cells.sort_unstable();
+        cells.dedup();
+        let mut data = Matrix::new(255, 255);
+        let mut width = 0;
+        for p in cells.iter() {
+            while data.height() <= p.x as usize {
+                data.add_row();
+            }
+            width = width.max(p.y as usize + 1);
+        }
+        for (id, p) in cells.iter().enumerate() {
+            data[(p.x as usize, p.y as usize)] = Some(id);
+        }
+        Image {
+            data,
+            labels: vec![FILLED; cells.len()],
+            points: cells,
             width,
         }
     }
 
+    // A rectangle of `width` x `height` pixels
+    pub fn rectangle(width: usize, height: usize) -> Self {
+        let cells =
+            (0..height).flat_map(|x| (0..width).map(move |y| Point::new(x as i16, y as i16)));
+        Image::from_cells(cells)
+    }
+
+    // A rectangle with a rectangular hole in its middle
+    pub fn with_hole(width: usize, height: usize, hole_width: usize, hole_height: usize) -> Self {
+        let top = height.saturating_sub(hole_height) / 2;
+        let left = width.saturating_sub(hole_width) / 2;
+        let in_hole = |x: usize, y: usize| {
+            (top..top + hole_height).contains(&x) && (left..left + hole_width).contains(&y)
+        };
+        let cells = (0..height)
+            .flat_map(|x| (0..width).map(move |y| (x, y)))
+            .filter(|&(x, y)| !in_hole(x, y))
+            .map(|(x, y)| Point::new(x as i16, y as i16));
+        Image::from_cells(cells)
+    }
+
+    // The pixels at most `radius` steps (horizontal or vertical) away from the middle one
+    pub fn diamond(radius: usize) -> Self {
+        let r = radius as i16;
+        let cells = (0..=2 * r)
+            .flat_map(|x| (0..=2 * r).map(move |y| Point::new(x, y)))
+            .filter(|p| (p.x - r).abs() + (p.y - r).abs() <= r);
+        Image::from_cells(cells)
+    }
+
+    // A staircase of `steps` rows, each of them one pixel longer than the one above
+    pub fn staircase(steps: usize) -> Self {
+        let cells = (0..steps).flat_map(|x| (0..=x).map(move |y| Point::new(x as i16, y as i16)));
+        Image::from_cells(cells)
+    }
+
+    // The pixels of the bitmap darker than `threshold`, or with `invert` the other ones
+    // Like with the text, only the first 255 x 255 pixels are taken
+    pub fn from_bitmap(bitmap: &Bitmap, threshold: u8, invert: bool) -> Self {
+        let cells = (0..bitmap.height().min(255))
+            .flat_map(|x| (0..bitmap.width().min(255)).map(move |y| (x, y)))
+            .filter(|&(x, y)| (bitmap.get(x, y) < threshold) != invert)
+            .map(|(x, y)| Point::new(x as i16, y as i16));
+        Image::from_cells(cells)
+    }
+
+    // Load all the images in the input, separated by empty lines
+    pub fn load_all(reader: impl std::io::BufRead, filled: char) -> Vec<Self> {
+        Image::load_all_labelled(reader, &[filled])
+    }
+
+    // Load all the images like with `load_labelled`
+    pub fn load_all_labelled(reader: impl std::io::BufRead, filled: &[char]) -> Vec<Self> {
+        let mut images = Vec::new();
+        let mut lines = reader.lines().map_while(Result::ok).peekable();
+        loop {
+            while lines.peek().is_some_and(|l| l.is_empty()) {
+                lines.next();
+            }
+            if lines.peek().is_none() {
+                return images;
+            }
+            let mut block = String::new();
+            while let Some(line) = lines.next_if(|l| !l.is_empty()) {
+                block += &line;
+                block.push('\n');
+            }
+            images.push(Image::load_labelled(block.as_bytes(), filled));
+        }
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -57,14 +169,46 @@ impl Image {
     }
 
     pub fn get_point_id(&self, x: i16, y: i16) -> Option<usize> {
-        if x < 0 || y < 0 {
+        if x < 0 || y < 0 || y as usize >= self.data.width() {
             None
         } else {
             self.data.get(x as usize, y as usize).and_then(|&o| o)
         }
     }
 
+    // The filled pixels split into connected regions
+    pub fn regions(&self) -> Vec<Vec<Point>> {
+        let mut visited = vec![false; self.points.len()];
+        let mut regions = Vec::new();
+        for (start, &p) in self.points.iter().enumerate() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut region = vec![p];
+            let mut i = 0;
+            while i < region.len() {
+                for n in region[i].neighbours().iter() {
+                    if let Some(id) = self.get_point_id(n.x, n.y) {
+                        if !visited[id] {
+                            visited[id] = true;
+                            region.push(*n);
+                        }
+                    }
+                }
+                i += 1;
+            }
+            regions.push(region);
+        }
+        regions
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Point> + '_ {
         self.points.iter()
     }
+
+    // The character of the filled pixel in the input
+    pub fn label(&self, id: usize) -> char {
+        self.labels[id]
+    }
 }
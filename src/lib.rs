@@ -1,4 +1,11 @@
+pub mod bitmap;
+pub mod checkpoint;
 pub mod image;
 pub mod matrix;
 pub mod tiles;
+mod zlib;
 pub mod linkage;
+mod pruning;
+pub mod render;
+pub mod shapes;
+pub mod verify;
@@ -1,11 +1,14 @@
 use std::collections::LinkedList;
+use std::io;
 use std::ops::{Index, IndexMut};
 use std::sync::Arc;
 use std::thread;
 
+use crate::checkpoint::{invalid, Checkpoint};
 use crate::image::Image;
-use crate::matrix::Matrix;
-use crate::tiles::Tiles;
+use crate::pruning::{reachable_sums, Parity, Regions};
+use crate::render::{ascii, Cover};
+use crate::tiles::{Placement, Tile, Tiles};
 
 const SOLUTINON_CAP: usize = 32;
 const LINKAGE_CAP: usize = 10000;
@@ -20,7 +23,7 @@ pub struct Node {
     extra: u32, // #nodes for column headers, pointer to respective column header otherwise
 }
 
-type Solution = Vec<u32>;
+pub type Solution = Vec<u32>;
 
 // a 2D linkage of nodes
 // first (width) nodes are column headers, followed by a main node pointing to
@@ -30,12 +33,48 @@ pub struct Linkage {
     width: u32,      // #columns
     pointcount: u32, // #columns representing points in the image
     data: Vec<Node>,
-    solution: Solution,   // a solution build so far
-    maxima: Arc<Vec<u8>>, // max #points in each tile class
-    max_possible: u32,    // size of the largest image that can be built with remaining tiles
-    remaining: u32,       // #remaining tiles
+    solution: Solution,            // a solution build so far
+    maxima: Arc<Vec<u8>>,          // max #points in each tile class
+    origins: Arc<Vec<(u32, u32)>>, // last node of each row and the index of its tile
+    max_possible: u32, // size of the largest image that can be built with remaining tiles
+    remaining: u32,    // #remaining tiles
     allow_repeat: bool,
-    unused: bool, // are some of the tiles unused?
+    parallel_depth: u16, // the search tree is split between threads up to this depth
+    regions: Option<Arc<Regions>>, // used for pruning by the sizes of uncovered regions
+    parity: Option<Arc<Parity>>, // used for pruning by the colorings of uncovered points
+    unused: bool,        // are some of the tiles unused?
+}
+
+// outcome of the sequential search in a subtree
+#[derive(PartialEq)]
+enum Search {
+    Done,
+    Stopped,
+    Mismatch, // the checkpoint does not match this linkage
+}
+
+// Estimated size of the search tree and #solutions, see Linkage::estimate
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Estimate {
+    pub nodes: f64,
+    pub solutions: f64,
+}
+
+// xorshift64* pseudorandom numbers, good enough for sampling the search tree
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        // the state must not be zero
+        Random((seed ^ 0x9e37_79b9_7f4a_7c15).max(1))
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (((self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) * n as u64) >> 32) as u32
+    }
 }
 
 struct LinkageIterRow<'a> {
@@ -46,13 +85,26 @@ struct LinkageIterRow<'a> {
 
 impl Linkage {
     pub fn build(image: &Image, tiles: &Tiles, allow_repeat: bool) -> Self {
+        Linkage::build_constrained(image, tiles, allow_repeat, |_, _| true)
+    }
+
+    // The linkage with only the placements of tiles allowed by the constraint, which is given
+    // the tile and the ids of the pixels of the image it would cover, in the order of the tile's
+    // points (the origin first)
+    pub fn build_constrained(
+        image: &Image,
+        tiles: &Tiles,
+        allow_repeat: bool,
+        allowed: impl Fn(&Tile, &[usize]) -> bool,
+    ) -> Self {
         let pointcount = image.pointcount();
         let width = pointcount + tiles.kinds_count();
         let mut buffer = Vec::with_capacity(32);
 
         let mut maxima = vec![0; tiles.kinds_count()];
         let mut linkage = Linkage::with_capacity(width as u32, pointcount as u32, LINKAGE_CAP);
-        for tile in tiles.iter() {
+        let mut origins = Vec::new();
+        for (index, tile) in tiles.iter().enumerate() {
             'imageloop: for (ord, point) in image.iter().enumerate() {
                 buffer.push(ord);
                 for p in tile.points.iter() {
@@ -63,9 +115,17 @@ impl Linkage {
                         continue 'imageloop;
                     }
                 }
+                // the colored pixels of the tile only onto the pixels labelled by their colors
+                let matching = (tile.colors.iter().zip(buffer.iter()))
+                    .all(|(c, &id)| c.is_none_or(|c| image.label(id) == c));
+                if !matching || !allowed(tile, &buffer) {
+                    buffer.clear();
+                    continue;
+                }
 
                 buffer.push(pointcount + tile.kind);
                 linkage.add_row(&buffer);
+                origins.push((linkage.data.len() as u32 - 1, index as u32));
 
                 let tilesize = tile.points.len() as u8 + 1;
                 if tilesize > maxima[tile.kind] {
@@ -88,6 +148,7 @@ impl Linkage {
         linkage.remaining = pointcount as u32;
         linkage.max_possible = maxima.iter().map(|&i| i as u32).sum();
         linkage.maxima = Arc::new(maxima);
+        linkage.origins = Arc::new(origins);
         linkage.allow_repeat = allow_repeat;
 
         linkage
@@ -110,9 +171,13 @@ impl Linkage {
             data,
             solution: Vec::with_capacity(SOLUTINON_CAP),
             maxima: Arc::new(Vec::with_capacity(0)),
+            origins: Arc::new(Vec::with_capacity(0)),
             remaining: 0,
             max_possible: 0,
             allow_repeat: false,
+            parallel_depth: MAX_PARALLEL_DEPTH,
+            regions: None,
+            parity: None,
             unused: false,
         }
     }
@@ -226,7 +291,7 @@ impl Linkage {
     fn find_min(&self) -> (u32, u32) {
         let head = self.width;
         let mut i = self[head].right;
-        let (mut min, mut mincol) = (std::u32::MAX, 0);
+        let (mut min, mut mincol) = (u32::MAX, 0);
         while i != head
             && ((!self.allow_repeat && !self.unused && self.remaining == self.max_possible)
                 || i < self.pointcount)
@@ -240,6 +305,65 @@ impl Linkage {
         (min, mincol)
     }
 
+    // can the search from the current state not lead to any solution?
+    fn is_dead_end(&self, min: u32) -> bool {
+        min == 0
+            || (!self.allow_repeat && self.max_possible < self.remaining)
+            || self.regions.as_ref().is_some_and(|r| !self.regions_fit(r))
+            || self.parity.as_ref().is_some_and(|p| !self.parity_fits(p))
+    }
+
+    fn is_uncovered(&self, col: u32) -> bool {
+        self[self[col].left].right == col
+    }
+
+    // tile classes that can still be placed
+    fn available_classes(&self) -> impl Iterator<Item = usize> + '_ {
+        (self.pointcount..self.width)
+            .filter(move |&col| {
+                self[col].extra > 0 && (self.allow_repeat || self.is_uncovered(col))
+            })
+            .map(move |col| (col - self.pointcount) as usize)
+    }
+
+    // can every connected region of uncovered points be covered by the remaining tiles?
+    fn regions_fit(&self, regions: &Regions) -> bool {
+        let components = regions.component_sizes(|i| self.is_uncovered(i));
+        let largest = components.iter().cloned().max().unwrap_or(0);
+
+        let available = self.available_classes().map(|c| &regions.sizes[c][..]);
+        let sums = reachable_sums(available, 0, largest as i32, self.allow_repeat);
+        components.iter().all(|&size| sums[size as usize])
+    }
+
+    // can the imbalances of the remaining tiles add up to the imbalance of the uncovered
+    // points in every coloring?
+    fn parity_fits(&self, parity: &Parity) -> bool {
+        let remaining = self.remaining as i32;
+        let total = if self.allow_repeat {
+            remaining
+        } else {
+            self.available_classes()
+                .map(|c| self.maxima[c] as i32)
+                .sum()
+        };
+        parity.colorings.iter().all(|coloring| {
+            let modulus = coloring.modulus as i32;
+            let zeros = (0..self.pointcount)
+                .filter(|&i| coloring.colors[i as usize] == 0 && self.is_uncovered(i))
+                .count() as i32;
+            let imbalance = modulus * zeros - remaining;
+
+            // a tile of `size` points has an imbalance from -size to (modulus - 1) * size
+            let (lo, hi) = (-total, (modulus - 1) * total);
+            let available = self
+                .available_classes()
+                .map(|c| &coloring.imbalances[c][..]);
+            let sums = reachable_sums(available, lo, hi, self.allow_repeat);
+            lo <= imbalance && imbalance <= hi && sums[(imbalance - lo) as usize]
+        })
+    }
+
     fn push_solution(&mut self, i: u32) {
         self.solution.push(i);
         let mut j = self[i].right;
@@ -259,6 +383,28 @@ impl Linkage {
         }
     }
 
+    // cover every column of the row containing node i and add it to the solution
+    fn select_row(&mut self, i: u32) {
+        let mut j = self[i].right;
+        while j != i {
+            let col = self[j].extra;
+            self.cover_col(col);
+            j = self[j].right;
+        }
+        self.push_solution(i);
+    }
+
+    // revert select_row
+    fn deselect_row(&mut self, i: u32) {
+        self.pop_solution();
+        let mut j = self[i].left;
+        while j != i {
+            let col = self[j].extra;
+            self.uncover_col(col);
+            j = self[j].left;
+        }
+    }
+
     fn solve_next(&mut self, depth: u16, find_all: bool) -> LinkedList<Solution> {
         let mut solutions = LinkedList::new();
         if self.is_empty() {
@@ -267,69 +413,46 @@ impl Linkage {
         }
 
         let (min, mincol) = self.find_min();
-        if min == 0 || (!self.allow_repeat && self.max_possible < self.remaining) {
+        if self.is_dead_end(min) {
             return solutions;
         }
         self.cover_col(mincol);
 
-        if depth < MAX_PARALLEL_DEPTH && find_all {
+        if depth < self.parallel_depth && find_all {
             let mut threads = Vec::with_capacity(min as usize - 1);
             let mut i = self[mincol].down;
             let second_last = self[mincol].up;
             while i != second_last {
                 let mut linkage = self.clone();
                 threads.push(thread::spawn(move || {
-                    let mut j = linkage[i].right;
-                    while j != i {
-                        let col = linkage[j].extra;
-                        linkage.cover_col(col);
-                        j = linkage[j].right;
-                    }
-                    linkage.push_solution(i);
+                    linkage.select_row(i);
                     linkage.solve_next(depth + 1, find_all)
                 }));
                 i = self[i].down;
             }
 
-            let mut j = self[i].right;
-            while j != i {
-                let col = self[j].extra;
-                self.cover_col(col);
-                j = self[j].right;
-            }
-            self.push_solution(i);
-            solutions.append(&mut self.solve_next(depth + 1, find_all));
+            self.select_row(i);
+            let mut last = self.solve_next(depth + 1, find_all);
 
-            threads
+            // keep the order of the branches, as if the search was sequential
+            let mut solutions = threads
                 .into_iter()
                 .map(|t| t.join().expect("A thread panicked!"))
                 .fold(solutions, |mut acc, mut s| {
                     acc.append(&mut s);
                     acc
-                })
+                });
+            solutions.append(&mut last);
+            solutions
         } else {
             let mut i = self[mincol].down;
             while i != mincol {
-                let mut j = self[i].right;
-                while j != i {
-                    let col = self[j].extra;
-                    self.cover_col(col);
-                    j = self[j].right;
-                }
-
-                self.push_solution(i);
+                self.select_row(i);
                 solutions.append(&mut self.solve_next(depth + 1, find_all));
                 if !solutions.is_empty() && !find_all {
                     return solutions;
                 }
-                self.pop_solution();
-
-                j = self[i].left;
-                while j != i {
-                    let col = self[j].extra;
-                    self.uncover_col(col);
-                    j = self[j].left;
-                }
+                self.deselect_row(i);
 
                 i = self[i].down;
             }
@@ -339,63 +462,298 @@ impl Linkage {
         }
     }
 
+    // sequential version of solve_next, only counting the solutions
+    // it starts at the branches given by `resume` (and `rows` chosen in them, if known),
+    // taking no other branches on the first `fixed` levels,
+    // and pushes every branch it takes onto the stacks in `checkpoint`
+    fn search_next(
+        &mut self,
+        resume: &[u32],
+        rows: &[u32],
+        fixed: usize,
+        checkpoint: &mut Checkpoint,
+        step: &mut impl FnMut(&Checkpoint) -> bool,
+    ) -> Search {
+        if self.is_empty() {
+            if !resume.is_empty() {
+                return Search::Mismatch;
+            }
+            checkpoint.count += 1;
+            return Search::Done;
+        }
+
+        let (min, mincol) = self.find_min();
+        if self.is_dead_end(min) {
+            return if resume.is_empty() {
+                Search::Done
+            } else {
+                Search::Mismatch
+            };
+        }
+        self.cover_col(mincol);
+
+        let first = resume.first().cloned().unwrap_or(0);
+        let mut result = if first < min {
+            Search::Done
+        } else {
+            Search::Mismatch
+        };
+        let mut i = self[mincol].down;
+        let mut branch = 0;
+        while i != mincol && result == Search::Done {
+            if branch == first && rows.first().is_some_and(|&r| r != i) {
+                result = Search::Mismatch;
+            } else if branch >= first {
+                self.select_row(i);
+                checkpoint.rows.push(i);
+                checkpoint.branches.push(branch);
+                result = if !step(checkpoint) {
+                    Search::Stopped
+                } else if branch == first {
+                    let rest = resume.get(1..).unwrap_or(&[]);
+                    let rows = rows.get(1..).unwrap_or(&[]);
+                    self.search_next(rest, rows, fixed.saturating_sub(1), checkpoint, step)
+                } else {
+                    self.search_next(&[], &[], 0, checkpoint, step)
+                };
+                self.deselect_row(i);
+                if result == Search::Done {
+                    checkpoint.rows.pop();
+                    checkpoint.branches.pop();
+                }
+                if fixed > 0 {
+                    break;
+                }
+            }
+            branch += 1;
+            i = self[i].down;
+        }
+        self.uncover_col(mincol);
+
+        result
+    }
+
+    // collect the branches taken to every node of the search tree at `depth`,
+    // or to solutions found above it
+    fn prefixes_next(&mut self, depth: usize, path: &mut Vec<u32>, prefixes: &mut Vec<Vec<u32>>) {
+        if self.is_empty() || path.len() == depth {
+            prefixes.push(path.clone());
+            return;
+        }
+
+        let (min, mincol) = self.find_min();
+        if self.is_dead_end(min) {
+            return;
+        }
+        self.cover_col(mincol);
+
+        let mut i = self[mincol].down;
+        let mut branch = 0;
+        while i != mincol {
+            self.select_row(i);
+            path.push(branch);
+            self.prefixes_next(depth, path, prefixes);
+            path.pop();
+            self.deselect_row(i);
+            branch += 1;
+            i = self[i].down;
+        }
+        self.uncover_col(mincol);
+    }
+
+    // follow a random path from the current node of the search tree down to a leaf,
+    // adding the estimates of the #nodes on every level and of the #solutions to `estimate`
+    fn sample_path(&mut self, random: &mut Random, estimate: &mut Estimate) {
+        let mut path = Vec::new();
+        let mut weight = 1.0;
+        estimate.nodes += weight;
+        loop {
+            if self.is_empty() {
+                estimate.solutions += weight;
+                break;
+            }
+            let (min, mincol) = self.find_min();
+            if self.is_dead_end(min) {
+                break;
+            }
+            self.cover_col(mincol);
+            let mut i = self[mincol].down;
+            for _ in 0..random.below(min) {
+                i = self[i].down;
+            }
+            self.select_row(i);
+            path.push((mincol, i));
+            weight *= min as f64;
+            estimate.nodes += weight;
+        }
+        for &(col, i) in path.iter().rev() {
+            self.deselect_row(i);
+            self.uncover_col(col);
+        }
+    }
+
     fn iter_row<'a>(&'a self, first: u32) -> LinkageIterRow<'a> {
         LinkageIterRow {
             first,
             current: first,
-            linkage: &self,
+            linkage: self,
         }
     }
 
+    // Prune the search by checking whether every connected region of the uncovered points
+    // can be covered by the remaining tiles, which pays off especially for larger tiles
+    pub fn enable_region_pruning(&mut self, image: &Image, tiles: &Tiles) {
+        self.regions = Some(Arc::new(Regions::new(image, tiles)));
+    }
+
+    // Prune the search by checking whether the remaining tiles can balance the colorings
+    // of the uncovered points, where each point (x, y) gets the color (x + y) mod k for every
+    // given modulus k (2 is the checkerboard coloring), the moduli below 2 are ignored
+    pub fn enable_parity_pruning(&mut self, image: &Image, tiles: &Tiles, moduli: &[u8]) {
+        self.parity = Some(Arc::new(Parity::new(image, tiles, moduli)));
+    }
+
+    // Split the search between threads up to the given depth of the search tree
+    // (0 means searching in a single thread); this does not affect the order of solutions
+    pub fn set_parallel_depth(&mut self, depth: u16) {
+        self.parallel_depth = depth;
+    }
+
+    // Find all solutions, or just the first one
+    // The solutions are always ordered by the branches taken to them in the search tree,
+    // i.e. by the order of the chosen rows in their columns, regardless of the threads
     pub fn solve(&mut self, find_all: bool) -> LinkedList<Solution> {
         self.solve_next(0, find_all)
     }
 
-    // Convert the solution into a readable form
-    pub fn show_solution(&self, solution: &Solution, image: &Image, tiles: &Tiles) -> String {
-        let mut canvas = Matrix::new_fixed(2 * image.width() + 1, 2 * image.height() + 1, ' ');
-        for &i in solution {
-            let mut r = i;
-            while self[r].extra < self.pointcount {
-                r = self[r].right
-            }
-            let name = tiles.name((self[r].extra - self.pointcount) as usize);
-
-            let mut row_iter = self
-                .iter_row(r)
-                .filter_map(|j| image.get_point(self[j].extra as usize))
-                .map(|p| (2 * p.x as usize + 1, 2 * p.y as usize + 1));
-            for (x, y) in &mut row_iter {
-                canvas[(x, y)] = name;
-                for &(x, y) in neighbours(x, y).into_iter() {
-                    canvas[(x, y)] = if canvas[(x, y)] == '/' { ' ' } else { '/' };
-                }
-            }
-            for (x, y) in row_iter {
-                for &(x, y) in neighbours(x, y).into_iter() {
-                    if canvas[(x, y)] == '/' {
-                        if x % 2 == 1 {
-                            canvas[(x, y)] = '|';
-                            canvas[(x - 1, y)] = '+';
-                            canvas[(x + 1, y)] = '+';
-                        } else {
-                            canvas[(x, y)] = '-';
-                            canvas[(x, y - 1)] = '+';
-                            canvas[(x, y + 1)] = '+';
-                        }
-                    }
-                }
+    // Count all solutions in a single thread, resuming from the position in `checkpoint`
+    // `step` is called with the current position whenever a branch is taken;
+    // if it returns false, the search stops and `checkpoint` can be used to resume it
+    // Returns whether the search has been exhausted
+    pub fn count_from(
+        &mut self,
+        checkpoint: &mut Checkpoint,
+        mut step: impl FnMut(&Checkpoint) -> bool,
+    ) -> io::Result<bool> {
+        if checkpoint.exhausted {
+            return Ok(true);
+        }
+        let resume = std::mem::take(&mut checkpoint.branches);
+        let rows = std::mem::take(&mut checkpoint.rows);
+        match self.search_next(&resume, &rows, 0, checkpoint, &mut step) {
+            Search::Done => {
+                checkpoint.exhausted = true;
+                Ok(true)
             }
+            Search::Stopped => Ok(false),
+            Search::Mismatch => Err(invalid(
+                "the checkpoint does not belong to this puzzle".to_string(),
+            )),
         }
+    }
 
-        canvas.iter_rows().fold("".to_string(), |acc, slice| {
-            format!("{}{}\n", acc, slice.iter().collect::<String>())
-        })
+    // Split the search into independent jobs: the branch indices taken on the way to
+    // every node of the search tree at `depth` (or to a solution found above it)
+    // The counts of solutions of all the jobs sum up to the total count
+    pub fn prefixes(&mut self, depth: usize) -> Vec<Vec<u32>> {
+        let mut prefixes = Vec::new();
+        self.prefixes_next(depth, &mut Vec::with_capacity(depth), &mut prefixes);
+        prefixes
     }
-}
 
-fn neighbours(x: usize, y: usize) -> [(usize, usize); 4] {
-    [(x - 1, y), (x, y - 1), (x + 1, y), (x, y + 1)]
+    // Count the solutions in the subtree of the search tree given by `prefix`
+    pub fn count_prefix(&mut self, prefix: &[u32]) -> io::Result<u64> {
+        let mut checkpoint = Checkpoint::new();
+        match self.search_next(prefix, &[], prefix.len(), &mut checkpoint, &mut |_| true) {
+            Search::Mismatch => Err(invalid(
+                "the job does not belong to this puzzle".to_string(),
+            )),
+            _ => Ok(checkpoint.count),
+        }
+    }
+
+    // Count all solutions, splitting the search between threads just like `solve`
+    pub fn count(&mut self) -> u64 {
+        let threads = self
+            .prefixes(self.parallel_depth as usize)
+            .into_iter()
+            .map(|prefix| {
+                let mut linkage = self.clone();
+                thread::spawn(move || {
+                    linkage
+                        .count_prefix(&prefix)
+                        .expect("A job of this puzzle does not belong to it!")
+                })
+            })
+            .collect::<Vec<_>>();
+        threads
+            .into_iter()
+            .map(|t| t.join().expect("A thread panicked!"))
+            .sum()
+    }
+
+    // Estimate the size of the search tree and the #solutions without searching through it,
+    // by Knuth's method: the averages over random paths from the root, where every node
+    // on a path stands for as many nodes as is the product of the #branches above it
+    pub fn estimate(&mut self, samples: u32, seed: u64) -> Estimate {
+        let mut random = Random::new(seed);
+        let mut estimate = Estimate::default();
+        for _ in 0..samples {
+            self.sample_path(&mut random, &mut estimate);
+        }
+        estimate.nodes /= samples.max(1) as f64;
+        estimate.solutions /= samples.max(1) as f64;
+        estimate
+    }
+
+    // #ways to place a tile onto the image, i.e. #rows of the exact cover problem
+    pub fn possible_placements(&self) -> usize {
+        self.origins.len()
+    }
+
+    // Find out which tile covers which pixel of the image in the solution
+    pub fn cover(&self, solution: &Solution, image: &Image) -> Cover {
+        let placements = self.placements(solution, image);
+        Cover::from_placements(image.width(), image.height(), &placements)
+    }
+
+    // Convert the solution into a readable form, drawn by render::ascii
+    pub fn show_solution(&self, solution: &Solution, image: &Image, tiles: &Tiles) -> String {
+        ascii::show(&self.cover(solution, image), tiles)
+    }
+
+    // Decode the solution into the tiles placed onto the image
+    pub fn placements(&self, solution: &Solution, image: &Image) -> Vec<Placement> {
+        solution
+            .iter()
+            .map(|&i| {
+                let mut r = i;
+                while self[r].extra < self.pointcount {
+                    r = self[r].right
+                }
+                let cells = self
+                    .iter_row(r)
+                    .filter_map(|j| image.get_point(self[j].extra as usize))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                Placement {
+                    class: (self[r].extra - self.pointcount) as usize,
+                    tile_index: self.tile_index(r),
+                    anchor: cells[0],
+                    cells,
+                }
+            })
+            .collect()
+    }
+
+    // index of the tile (among all the tiles loaded) placed by the row ending with node r
+    fn tile_index(&self, r: u32) -> usize {
+        let row = self
+            .origins
+            .binary_search_by_key(&r, |&(last, _)| last)
+            .expect("Not the last node of a row!");
+        self.origins[row].1 as usize
+    }
 }
 
 impl Index<u32> for Linkage {
@@ -1,68 +1,1194 @@
+use polyomino::bitmap::Bitmap;
+use polyomino::checkpoint::Checkpoint;
 use polyomino::image::Image;
 use polyomino::linkage::Linkage;
-use polyomino::tiles::Tiles;
+use polyomino::render::ansi::{self, Palette};
+use polyomino::render::{ascii, html, json, letters, png, svg, tikz, unicode, Cover};
+use polyomino::shapes::Symmetry;
+use polyomino::tiles::{Placement, Tiles};
+use polyomino::verify::verify;
 
-use std::fs::File;
-use std::io::{stdin, BufReader};
-use std::path::PathBuf;
-use std::time::Instant;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{
+    stdin, stdout, BufRead, BufReader, BufWriter, Error, ErrorKind, IsTerminal, Read, Write,
+};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
-struct Opt {
-    /// Print all solutions
-    #[structopt(short = "A", long = "all")]
-    print_all: bool,
-    /// Finish after finding the first solution
-    #[structopt(short = "O", long = "one")]
-    find_one: bool,
+enum Command {
+    /// Find the covers of the image by the blocks and print them
+    #[structopt(name = "solve")]
+    Solve {
+        #[structopt(flatten)]
+        puzzle: Puzzle,
+        #[structopt(flatten)]
+        pruning: Pruning,
+        /// Print all solutions
+        #[structopt(short = "A", long = "all")]
+        print_all: bool,
+        /// Finish after finding the first solution
+        #[structopt(short = "O", long = "one")]
+        find_one: bool,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// Count the covers of the image, optionally resumably
+    #[structopt(name = "count")]
+    Count {
+        #[structopt(flatten)]
+        puzzle: Puzzle,
+        #[structopt(flatten)]
+        pruning: Pruning,
+        /// Count in a single thread, periodically saving the progress to <checkpoint>
+        #[structopt(long = "checkpoint", parse(from_os_str))]
+        checkpoint: Option<PathBuf>,
+        /// Count in a single thread, resuming from the progress saved in <resume>
+        #[structopt(long = "resume", parse(from_os_str))]
+        resume: Option<PathBuf>,
+        /// Save the progress every <interval> seconds
+        #[structopt(long = "checkpoint-interval", default_value = "60")]
+        interval: u64,
+    },
+    /// List independent jobs the search can be split into, one per line
+    #[structopt(name = "jobs")]
+    Jobs {
+        #[structopt(flatten)]
+        puzzle: Puzzle,
+        #[structopt(flatten)]
+        pruning: Pruning,
+        /// Split the search tree at <depth>
+        #[structopt(short = "d", long = "depth", default_value = "2")]
+        depth: usize,
+    },
+    /// Count the solutions of a single job, as listed by `jobs`
+    #[structopt(name = "run")]
+    Run {
+        #[structopt(flatten)]
+        puzzle: Puzzle,
+        #[structopt(flatten)]
+        pruning: Pruning,
+        job: String,
+    },
+    /// Check the solutions in <file>, given as grids of letters or a JSON document
+    #[structopt(name = "verify")]
+    Verify {
+        #[structopt(flatten)]
+        puzzle: Puzzle,
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Draw the solutions in <file> (grids of letters or a JSON document), or the blocks
+    #[structopt(name = "render")]
+    Render {
+        #[structopt(flatten)]
+        blocks: Blocks,
+        /// Draw the first rendition of every class of blocks instead
+        #[structopt(long = "classes")]
+        classes: bool,
+        #[structopt(flatten)]
+        output: Output,
+        /// Read the solutions from <file>, defaults to standard input
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+    /// Write all the polyominoes with <size> pixels as blocks
+    #[structopt(name = "generate-tiles")]
+    GenerateTiles {
+        size: usize,
+        /// Renditions of the same block: free (rotated and reflected), one-sided (rotated) or fixed
+        #[structopt(long = "symmetry", default_value = "free")]
+        symmetry: Symmetry,
+        /// Write the blocks to <output>
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Solve every image in <inputs> (files of images separated by empty lines, pictures, or
+    /// directories of such files) with the same blocks, summing the results up in a table
+    #[structopt(name = "batch")]
+    Batch {
+        /// Allow repetition of blocks
+        #[structopt(short = "r", long = "allow-repeat")]
+        allow_repeat: bool,
+        #[structopt(flatten)]
+        pixels: Pixels,
+        #[structopt(flatten)]
+        blocks: Blocks,
+        #[structopt(flatten)]
+        pruning: Pruning,
+        /// Only find out whether there is a solution to every image
+        #[structopt(short = "O", long = "one")]
+        find_one: bool,
+        /// Read the images from <inputs>, defaults to standard input
+        #[structopt(parse(from_os_str))]
+        inputs: Vec<PathBuf>,
+    },
+    /// Solve the generated images of <shape>: rectangles, holes (rectangles with a hole
+    /// in the middle), diamonds or staircases
+    #[structopt(name = "sweep")]
+    Sweep {
+        shape: Shape,
+        /// Only the images with <area> filled pixels
+        #[structopt(long = "area")]
+        area: Option<usize>,
+        /// Up to <max> pixels long sides of the rectangles, radius of the diamonds or steps of the staircases
+        #[structopt(long = "max")]
+        max: Option<usize>,
+        /// Size of the hole in the rectangles, as <width>x<height>
+        #[structopt(long = "hole", default_value = "2x2")]
+        hole: Size,
+        /// Allow repetition of blocks
+        #[structopt(short = "r", long = "allow-repeat")]
+        allow_repeat: bool,
+        #[structopt(flatten)]
+        blocks: Blocks,
+        #[structopt(flatten)]
+        pruning: Pruning,
+        /// Only find out whether there is a solution to every image
+        #[structopt(short = "O", long = "one")]
+        find_one: bool,
+    },
+    /// Print statistics of the image and the blocks
+    #[structopt(name = "info")]
+    Info {
+        #[structopt(flatten)]
+        puzzle: Puzzle,
+    },
+    /// Estimate the size of the search and the number of solutions by random sampling
+    #[structopt(name = "estimate")]
+    Estimate {
+        #[structopt(flatten)]
+        puzzle: Puzzle,
+        #[structopt(flatten)]
+        pruning: Pruning,
+        /// Follow <samples> random paths in the search tree
+        #[structopt(long = "samples", default_value = "1000")]
+        samples: u32,
+        /// Seed of the random paths
+        #[structopt(long = "seed", default_value = "1")]
+        seed: u64,
+    },
+}
+
+// The image to cover and the blocks to cover it with
+#[derive(StructOpt)]
+struct Puzzle {
     /// Allow repetition of blocks
     #[structopt(short = "r", long = "allow-repeat")]
     allow_repeat: bool,
     /// Read input from <inputfile>, defaults to standard input
     #[structopt(short = "i", parse(from_os_str))]
     inputfile: Option<PathBuf>,
-    /// Interpret <wchar> as "filled" pixel in the input
+    /// Cover a rectangle of <rect> pixels, as <width>x<height>, instead of reading the input
+    #[structopt(long = "rect", conflicts_with = "inputfile")]
+    rect: Option<Size>,
+    /// Cover the image drawn in <board>, with rows separated by `/`, instead of reading the input
+    #[structopt(long = "board", raw(conflicts_with_all = r#"&["inputfile", "rect"]"#))]
+    board: Option<String>,
+    #[structopt(flatten)]
+    pixels: Pixels,
+    #[structopt(flatten)]
+    blocks: Blocks,
+}
+
+// How the filled pixels are read from the input and which blocks may cover them
+#[derive(StructOpt)]
+struct Pixels {
+    /// Interpret the characters of <wchar> as "filled" pixels in the input, labelled by them
     #[structopt(short = "w", default_value = "x")]
-    wchar: char,
+    wchar: String,
+    /// Cover the pixels labelled <label> only by the blocks of <classes>, given as <label>=<classes>
+    #[structopt(long = "region", raw(number_of_values = "1"))]
+    regions: Vec<Region>,
+    /// Fill the pixels of a PBM, PGM, PPM or PNG input darker than <threshold> (0 to 255)
+    #[structopt(long = "threshold", default_value = "128")]
+    threshold: u8,
+    /// Fill the light pixels of a PBM, PGM, PPM or PNG input instead of the dark ones
+    #[structopt(long = "invert")]
+    invert: bool,
+}
+
+impl Pixels {
+    fn filled(&self) -> Vec<char> {
+        self.wchar.chars().collect()
+    }
+
+    // The images in the data: a picture, or images drawn by text separated by empty lines
+    fn images(&self, data: &[u8]) -> std::io::Result<Vec<Image>> {
+        if Bitmap::recognize(data) {
+            Ok(vec![self.bitmap(data)?])
+        } else {
+            Ok(Image::load_all_labelled(data, &self.filled()))
+        }
+    }
+
+    fn bitmap(&self, data: &[u8]) -> std::io::Result<Image> {
+        let bitmap = Bitmap::read(data)?;
+        if bitmap.width() > 255 || bitmap.height() > 255 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "the image has {} x {} pixels, at most 255 x 255 are allowed",
+                    bitmap.width(),
+                    bitmap.height()
+                ),
+            ));
+        }
+        Ok(Image::from_bitmap(&bitmap, self.threshold, self.invert))
+    }
+}
+
+// The blocks, from a file or one of the sets built into the program
+#[derive(StructOpt)]
+struct Blocks {
     /// Load blocks from <blockfile>
-    #[structopt(short = "b", default_value = "tiles/pentomino", parse(from_os_str))]
-    blockfile: PathBuf,
+    #[structopt(short = "b", parse(from_os_str))]
+    blockfile: Option<PathBuf>,
+    /// Use the built-in set of blocks <tiles> instead, defaults to pentomino
+    #[structopt(long = "tiles", conflicts_with = "blockfile")]
+    tiles: Option<String>,
+}
+
+impl Blocks {
+    fn load(&self) -> std::io::Result<Tiles> {
+        if let Some(ref blockfile) = self.blockfile {
+            return Ok(Tiles::load(BufReader::new(File::open(blockfile)?)));
+        }
+        let name = self.tiles.as_deref().unwrap_or("pentomino");
+        Tiles::builtin(name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "unknown set of blocks `{}`, expected one of: {}",
+                    name,
+                    Tiles::builtin_names().collect::<Vec<_>>().join(", ")
+                ),
+            )
+        })
+    }
+}
+
+#[derive(StructOpt)]
+struct Pruning {
+    /// Prune branches leaving regions that can't be covered by the remaining blocks
+    #[structopt(long = "prune-regions")]
+    prune_regions: bool,
+    /// Prune branches where the blocks can't balance the coloring (x + y) mod <parity>
+    #[structopt(long = "parity", parse(try_from_str = "modulus"))]
+    parity: Vec<u8>,
+}
+
+#[derive(StructOpt)]
+struct Output {
+    /// Print the solutions in <format>: text, ansi (colored text), letters, svg, png, tikz, html (a gallery) or json
+    #[structopt(short = "f", long = "format", default_value = "text")]
+    format: Format,
+    /// Write the solution to <output>, or all of them into numbered files in the directory <output>
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Draw the text with <style> characters: ascii or unicode
+    #[structopt(long = "style", default_value = "ascii")]
+    style: Style,
+    /// Size of a pixel in the graphical formats
+    #[structopt(
+        long = "cell-size",
+        default_value = "20",
+        parse(try_from_str = "cell_size")
+    )]
+    cell_size: usize,
+    /// Label the tiles by their classes in the graphical formats
+    #[structopt(long = "labels")]
+    labels: bool,
+}
+
+enum Format {
+    Text,
+    Ansi,
+    Letters,
+    Svg,
+    Png,
+    Tikz,
+    Html,
+    Json,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Text | Format::Ansi | Format::Letters => "txt",
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Tikz => "tex",
+            Format::Html => "html",
+            Format::Json => "json",
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "ansi" => Ok(Format::Ansi),
+            "letters" => Ok(Format::Letters),
+            "svg" => Ok(Format::Svg),
+            "png" => Ok(Format::Png),
+            "tikz" => Ok(Format::Tikz),
+            "html" => Ok(Format::Html),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format `{}`", s)),
+        }
+    }
+}
+
+enum Shape {
+    Rectangles,
+    Holes,
+    Diamonds,
+    Staircases,
+}
+
+impl std::str::FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rectangles" => Ok(Shape::Rectangles),
+            "holes" => Ok(Shape::Holes),
+            "diamonds" => Ok(Shape::Diamonds),
+            "staircases" => Ok(Shape::Staircases),
+            _ => Err(format!("unknown shape `{}`", s)),
+        }
+    }
+}
+
+// Size of a rectangle, written as <width>x<height>
+#[derive(Clone, Copy)]
+struct Size {
+    width: usize,
+    height: usize,
 }
 
+impl std::str::FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid size `{}`, expected e.g. 10x6 with sides of 1 to 255 pixels",
+                s
+            )
+        };
+        let mut sides = s.split('x').map(|side| side.parse::<usize>());
+        match (sides.next(), sides.next(), sides.next()) {
+            (Some(Ok(width)), Some(Ok(height)), None)
+                if (1..=255).contains(&width) && (1..=255).contains(&height) =>
+            {
+                Ok(Size { width, height })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// A modulus of the colorings for --parity, with a single color nothing can be unbalanced
+fn modulus(s: &str) -> Result<u8, String> {
+    match s.parse() {
+        Ok(modulus) if modulus >= 2 => Ok(modulus),
+        _ => Err(format!("invalid modulus `{}`, expected 2 to 255", s)),
+    }
+}
+
+// A size of the pixels in the graphical formats, at least 1
+fn cell_size(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(size) if size >= 1 => Ok(size),
+        _ => Err(format!(
+            "invalid cell size `{}`, expected a positive number",
+            s
+        )),
+    }
+}
+
+// Pixels with the given label, to be covered only by the given classes of blocks
+struct Region {
+    label: char,
+    classes: Vec<String>,
+}
+
+impl std::str::FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid region `{}`, expected e.g. a=F,P", s);
+        let (label, classes) = s.split_once('=').ok_or_else(invalid)?;
+        let mut chars = label.chars();
+        match (chars.next(), chars.next()) {
+            (Some(label), None) if !classes.is_empty() => Ok(Region {
+                label,
+                classes: classes.split(',').map(str::to_string).collect(),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+enum Style {
+    Ascii,
+    Unicode,
+}
+
+impl std::str::FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(Style::Ascii),
+            "unicode" => Ok(Style::Unicode),
+            _ => Err(format!("unknown style `{}`", s)),
+        }
+    }
+}
+
+// Number of branches taken between checking whether the progress should be saved
+const CHECKPOINT_STEPS: u64 = 1 << 16;
+
+// The job listed by `jobs` taking no branches, at depth 0, rather than an empty line
+const WHOLE_SEARCH: &str = "-";
+
 fn main() -> std::io::Result<()> {
-    let opt = Opt::from_args();
+    match Command::from_args() {
+        Command::Solve {
+            puzzle,
+            pruning,
+            print_all,
+            find_one,
+            output,
+        } => solve(&puzzle, &pruning, print_all, find_one, &output),
+        Command::Count {
+            puzzle,
+            pruning,
+            checkpoint,
+            resume,
+            interval,
+        } => {
+            let (image, tiles) = load(&puzzle)?;
+            let start = Instant::now();
+            let mut linkage = build(&puzzle, &pruning, &image, &tiles)?;
+            if checkpoint.is_some() || resume.is_some() {
+                let interval = Duration::from_secs(interval);
+                count(&mut linkage, &checkpoint, &resume, interval, start)?;
+            } else {
+                let len = linkage.count();
+                println!(
+                    "{} solution{}, counted in: {:?}",
+                    len,
+                    if len != 1 { "s" } else { "" },
+                    start.elapsed()
+                );
+            }
+            Ok(())
+        }
+        Command::Jobs {
+            puzzle,
+            pruning,
+            depth,
+        } => {
+            let (image, tiles) = load(&puzzle)?;
+            let mut linkage = build(&puzzle, &pruning, &image, &tiles)?;
+            for prefix in linkage.prefixes(depth) {
+                let job = prefix.iter().map(u32::to_string).collect::<Vec<_>>();
+                match job.is_empty() {
+                    true => println!("{}", WHOLE_SEARCH),
+                    false => println!("{}", job.join(",")),
+                }
+            }
+            Ok(())
+        }
+        Command::Run {
+            puzzle,
+            pruning,
+            job,
+        } => {
+            let (image, tiles) = load(&puzzle)?;
+            let mut linkage = build(&puzzle, &pruning, &image, &tiles)?;
+            let prefix = job
+                .split(',')
+                .filter(|&s| !s.is_empty() && s != WHOLE_SEARCH)
+                .map(|s| s.parse())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+            println!("{}", linkage.count_prefix(&prefix)?);
+            Ok(())
+        }
+        Command::Verify { puzzle, file } => {
+            let (image, tiles) = load(&puzzle)?;
+            check(&puzzle, &file, &image, &tiles)
+        }
+        Command::Render {
+            blocks,
+            classes,
+            output,
+            file,
+        } => {
+            let tiles = blocks.load()?;
+            if classes {
+                if let Format::Json | Format::Html = output.format {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "the tiles can't be drawn in the json or html format",
+                    ));
+                }
+                let drawing = draw(&output, &Cover::from_tiles(&tiles), &tiles);
+                return match output.output {
+                    Some(ref path) => fs::write(path, drawing),
+                    None => stdout().write_all(&drawing),
+                };
+            }
+            let solutions = match file {
+                Some(ref f) => read_solutions(BufReader::new(File::open(f)?), &tiles)?,
+                None => read_solutions(BufReader::new(stdin().lock()), &tiles)?,
+            };
+            let cells = solutions.iter().flatten().flat_map(|p| p.cells.iter());
+            let width = cells.clone().map(|p| p.y as usize + 1).max().unwrap_or(0);
+            let height = cells.map(|p| p.x as usize + 1).max().unwrap_or(0);
+            let len = solutions.len();
+            let all = len > 1;
+            let time = Duration::default();
+            write_solutions(
+                &output, &solutions, width, height, &tiles, len, time, true, all,
+            )
+        }
+        Command::GenerateTiles {
+            size,
+            symmetry,
+            output,
+        } => {
+            let tiles = Tiles::generate(size, symmetry);
+            eprintln!(
+                "{} classes, {} renditions",
+                tiles.kinds_count(),
+                tiles.iter().count()
+            );
+            match output {
+                Some(path) => {
+                    let mut writer = BufWriter::new(File::create(path)?);
+                    tiles.save(&mut writer)?;
+                    writer.flush()
+                }
+                None => tiles.save(stdout().lock()),
+            }
+        }
+        Command::Batch {
+            allow_repeat,
+            pixels,
+            blocks,
+            pruning,
+            find_one,
+            inputs,
+        } => {
+            let tiles = blocks.load()?;
+            let images = load_images(&inputs, &pixels)?;
+            let labelled = images.iter().map(|(_, image)| image).collect::<Vec<_>>();
+            let allowed = region_classes(&pixels, &labelled, &tiles)?;
+            batch(&images, &tiles, allow_repeat, &allowed, &pruning, find_one)
+        }
+        Command::Sweep {
+            shape,
+            area,
+            max,
+            hole,
+            allow_repeat,
+            blocks,
+            pruning,
+            find_one,
+        } => {
+            let tiles = blocks.load()?;
+            let images = sweep(&shape, area, max, hole);
+            batch(
+                &images,
+                &tiles,
+                allow_repeat,
+                &HashMap::new(),
+                &pruning,
+                find_one,
+            )
+        }
+        Command::Info { puzzle } => info(&puzzle),
+        Command::Estimate {
+            puzzle,
+            pruning,
+            samples,
+            seed,
+        } => {
+            let (image, tiles) = load(&puzzle)?;
+            let start = Instant::now();
+            let mut linkage = build(&puzzle, &pruning, &image, &tiles)?;
+            let estimate = linkage.estimate(samples, seed);
+            println!(
+                "~{:.0} nodes of the search tree, ~{:.1} solutions, estimated from {} paths in: {:?}",
+                estimate.nodes,
+                estimate.solutions,
+                samples,
+                start.elapsed()
+            );
+            Ok(())
+        }
+    }
+}
 
-    let f = File::open(opt.blockfile)?;
-    let f = BufReader::new(f);
-    let tiles = Tiles::load(f);
+// Load the image and the blocks of the puzzle
+fn load(puzzle: &Puzzle) -> std::io::Result<(Image, Tiles)> {
+    let tiles = puzzle.blocks.load()?;
 
-    let image = match opt.inputfile {
-        Some(f) => {
-            let i = BufReader::new(File::open(f)?);
-            Image::load(i, opt.wchar)
+    if let Some(ref rect) = puzzle.rect {
+        return Ok((Image::rectangle(rect.width, rect.height), tiles));
+    }
+    let filled = puzzle.pixels.filled();
+    if let Some(ref board) = puzzle.board {
+        let rows = board.replace('/', "\n");
+        return Ok((Image::load_labelled(rows.as_bytes(), &filled), tiles));
+    }
+    let data = match puzzle.inputfile {
+        Some(ref f) => fs::read(f)?,
+        None => {
+            let mut data = Vec::new();
+            stdin().lock().read_to_end(&mut data)?;
+            data
         }
-        None => Image::load(BufReader::new(stdin().lock()), opt.wchar),
     };
+    if Bitmap::recognize(&data) {
+        Ok((puzzle.pixels.bitmap(&data)?, tiles))
+    } else {
+        Ok((Image::load_labelled(&data[..], &filled), tiles))
+    }
+}
+
+fn build(
+    puzzle: &Puzzle,
+    pruning: &Pruning,
+    image: &Image,
+    tiles: &Tiles,
+) -> std::io::Result<Linkage> {
+    let allowed = region_classes(&puzzle.pixels, &[image], tiles)?;
+    let mut linkage = constrained(image, tiles, puzzle.allow_repeat, &allowed);
+    prune(&mut linkage, pruning, image, tiles);
+    Ok(linkage)
+}
+
+// The linkage with the blocks covering the labelled pixels restricted to the allowed classes
+fn constrained(
+    image: &Image,
+    tiles: &Tiles,
+    allow_repeat: bool,
+    allowed: &HashMap<char, Vec<usize>>,
+) -> Linkage {
+    Linkage::build_constrained(image, tiles, allow_repeat, |tile, ids| {
+        ids.iter().all(|&id| {
+            allowed
+                .get(&image.label(id))
+                .is_none_or(|classes| classes.contains(&tile.kind))
+        })
+    })
+}
+
+// The classes of blocks allowed to cover the pixels with every label given by --region,
+// which must be the label of some pixels of the images
+fn region_classes(
+    pixels: &Pixels,
+    images: &[&Image],
+    tiles: &Tiles,
+) -> std::io::Result<HashMap<char, Vec<usize>>> {
+    let mut allowed = HashMap::<char, Vec<usize>>::new();
+    for region in pixels.regions.iter() {
+        let labelled =
+            |image: &&Image| (0..image.pointcount()).any(|id| image.label(id) == region.label);
+        if !images.iter().any(labelled) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "no pixel labelled `{}` for --region, the labels are the characters of -w",
+                    region.label
+                ),
+            ));
+        }
+        let classes = allowed.entry(region.label).or_default();
+        for name in region.classes.iter() {
+            classes.push(tiles.kind(name).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("unknown block `{}` in --region", name),
+                )
+            })?);
+        }
+    }
+    Ok(allowed)
+}
+
+fn prune(linkage: &mut Linkage, pruning: &Pruning, image: &Image, tiles: &Tiles) {
+    if pruning.prune_regions {
+        linkage.enable_region_pruning(image, tiles);
+    }
+    if !pruning.parity.is_empty() {
+        linkage.enable_parity_pruning(image, tiles, &pruning.parity);
+    }
+}
+
+fn solve(
+    puzzle: &Puzzle,
+    pruning: &Pruning,
+    print_all: bool,
+    find_one: bool,
+    output: &Output,
+) -> std::io::Result<()> {
+    if let (Format::Png, None) = (&output.format, &output.output) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "the png format needs an --output file",
+        ));
+    }
+    let (image, tiles) = load(puzzle)?;
 
     let start = Instant::now();
-    let mut linkage = Linkage::build(&image, &tiles, opt.allow_repeat);
-    let solutions = linkage.solve(!opt.find_one);
+    let mut linkage = build(puzzle, pruning, &image, &tiles)?;
+    let solutions = linkage.solve(!find_one);
     let duration = start.elapsed();
     let len = solutions.len();
-    for s in solutions.iter().take(if opt.print_all { len } else { 1 }) {
-        println!("{}", linkage.show_solution(s, &image, &tiles))
-    }
-    println!(
+    let shown = if print_all { len } else { 1 };
+    let placements = solutions
+        .iter()
+        .take(shown)
+        .map(|s| linkage.placements(s, &image))
+        .collect::<Vec<_>>();
+    let (width, height) = (image.width(), image.height());
+    write_solutions(
+        output,
+        &placements,
+        width,
+        height,
+        &tiles,
+        len,
+        duration,
+        !find_one,
+        print_all,
+    )?;
+
+    let mut summary = format!(
         "{} solution{}, found in: {:?}",
         len,
         if len > 1 { "s" } else { "" },
         duration
     );
-    if solutions.len() == 0 {
-        println!("Allowing repetition (-r flag) could help find some.");
+    if solutions.is_empty() {
+        summary += "\nAllowing repetition (-r flag) could help find some.";
+    }
+    // keep the output of the other formats valid
+    match output.format {
+        Format::Text | Format::Ansi => println!("{}", summary),
+        _ => eprintln!("{}", summary),
+    }
+    Ok(())
+}
+
+// Write the solutions in the chosen format: all of them into one JSON or HTML document,
+// into numbered files in the output directory if `all` is set, or one after another
+// `count` is the number of solutions found in `time`, of which only some may be given
+#[allow(clippy::too_many_arguments)]
+fn write_solutions(
+    output: &Output,
+    solutions: &[Vec<Placement>],
+    width: usize,
+    height: usize,
+    tiles: &Tiles,
+    count: usize,
+    time: Duration,
+    exhaustive: bool,
+    all: bool,
+) -> std::io::Result<()> {
+    let covers = || {
+        solutions
+            .iter()
+            .map(|p| Cover::from_placements(width, height, p))
+    };
+    let document = match output.format {
+        Format::Json => Some(json::show(solutions, tiles, count, time, exhaustive)),
+        Format::Html => {
+            let covers = covers().collect::<Vec<_>>();
+            Some(html::show(&covers, tiles, count, output.cell_size))
+        }
+        _ => None,
+    };
+
+    match (document, &output.output) {
+        (Some(document), Some(path)) => fs::write(path, document)?,
+        (Some(document), None) => print!("{}", document),
+        (None, Some(path)) if all => {
+            fs::create_dir_all(path)?;
+            let digits = count.to_string().len();
+            for (n, cover) in covers().enumerate() {
+                let name = format!("{:0w$}.{}", n + 1, output.format.extension(), w = digits);
+                fs::write(path.join(name), draw(output, &cover, tiles))?;
+            }
+        }
+        (None, Some(path)) => {
+            if let Some(cover) = covers().next() {
+                fs::write(path, draw(output, &cover, tiles))?;
+            }
+        }
+        (None, None) => {
+            for cover in covers() {
+                println!("{}", String::from_utf8_lossy(&draw(output, &cover, tiles)));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Draw the covered image in the chosen format
+fn draw(output: &Output, cover: &Cover, tiles: &Tiles) -> Vec<u8> {
+    let text = || match output.style {
+        Style::Ascii => ascii::show(cover, tiles),
+        Style::Unicode => unicode::show(cover, tiles),
+    };
+    match output.format {
+        Format::Text => text().into_bytes(),
+        Format::Ansi if output.output.is_some() || !stdout().is_terminal() => text().into_bytes(),
+        Format::Ansi => ansi::show(cover, tiles, Palette::detect()).into_bytes(),
+        Format::Letters => letters::show(cover, tiles).into_bytes(),
+        Format::Svg => svg::show(cover, tiles, output.cell_size, output.labels).into_bytes(),
+        Format::Png => png::show(cover, output.cell_size),
+        Format::Tikz => tikz::show(cover, tiles, output.cell_size, output.labels).into_bytes(),
+        Format::Json | Format::Html => {
+            unreachable!("All the solutions are described by one JSON or HTML document")
+        }
+    }
+}
+
+// Read solutions given either as a JSON document or as grids of letters
+fn read_solutions(mut reader: impl BufRead, tiles: &Tiles) -> std::io::Result<Vec<Vec<Placement>>> {
+    let is_json = reader.fill_buf()?.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+    if is_json {
+        json::parse(reader, tiles)
+    } else {
+        letters::parse_all(reader, tiles)
+    }
+}
+
+// Verify the solutions read from the file, reporting each of them
+fn check(puzzle: &Puzzle, file: &Path, image: &Image, tiles: &Tiles) -> std::io::Result<()> {
+    let solutions = read_solutions(BufReader::new(File::open(file)?), tiles)?;
+    let allowed = region_classes(&puzzle.pixels, &[image], tiles)?;
+
+    let mut invalid = 0;
+    for (n, placements) in solutions.iter().enumerate() {
+        // a pixel of a region covered by a block not allowed there
+        let outside_region = placements.iter().find_map(|placement| {
+            placement.cells.iter().find(|p| {
+                image
+                    .get_point_id(p.x, p.y)
+                    .and_then(|id| allowed.get(&image.label(id)))
+                    .is_some_and(|classes| !classes.contains(&placement.class))
+            })
+        });
+        match (
+            verify(image, tiles, puzzle.allow_repeat, placements),
+            outside_region,
+        ) {
+            (Err(e), _) => {
+                println!("solution {}: invalid, {}", n + 1, e);
+                invalid += 1;
+            }
+            (Ok(()), Some(p)) => {
+                println!(
+                    "solution {}: invalid, pixel [{}, {}] covered by a block not allowed in its region",
+                    n + 1,
+                    p.x,
+                    p.y
+                );
+                invalid += 1;
+            }
+            (Ok(()), None) => println!("solution {}: valid", n + 1),
+        }
+    }
+    if invalid > 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{} of {} solutions are invalid", invalid, solutions.len()),
+        ));
+    }
+    Ok(())
+}
+
+// Load the images from the files, all the files in the directories or the standard input,
+// named by the files they come from and by their order in them
+fn load_images(inputs: &[PathBuf], pixels: &Pixels) -> std::io::Result<Vec<(String, Image)>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let mut entries = fs::read_dir(input)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.retain(|p| p.is_file());
+            entries.sort();
+            files.append(&mut entries);
+        } else {
+            files.push(input.clone());
+        }
+    }
+
+    let mut images = Vec::new();
+    let mut name_all = |name: String, loaded: Vec<Image>| {
+        let several = loaded.len() > 1;
+        for (n, image) in loaded.into_iter().enumerate() {
+            match several {
+                true => images.push((format!("{}#{}", name, n + 1), image)),
+                false => images.push((name.clone(), image)),
+            }
+        }
+    };
+    if inputs.is_empty() {
+        let mut data = Vec::new();
+        stdin().lock().read_to_end(&mut data)?;
+        name_all("stdin".to_string(), pixels.images(&data)?);
+    }
+    for file in files {
+        let loaded = pixels.images(&fs::read(&file)?)?;
+        name_all(file.display().to_string(), loaded);
+    }
+    Ok(images)
+}
+
+// Solve the images one by one, printing a row of the table for each of them
+fn batch(
+    images: &[(String, Image)],
+    tiles: &Tiles,
+    allow_repeat: bool,
+    allowed: &HashMap<char, Vec<usize>>,
+    pruning: &Pruning,
+    find_one: bool,
+) -> std::io::Result<()> {
+    let width = images
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+    println!(
+        "{:w$}  {:>7}  {:>6}  {:>9}  time",
+        "image",
+        "size",
+        "pixels",
+        if find_one { "tileable" } else { "solutions" },
+        w = width
+    );
+
+    let start = Instant::now();
+    let mut tileable = 0;
+    for (name, image) in images {
+        let puzzle_start = Instant::now();
+        let mut linkage = constrained(image, tiles, allow_repeat, allowed);
+        prune(&mut linkage, pruning, image, tiles);
+        let (count, result) = if find_one {
+            let found = !linkage.solve(false).is_empty();
+            (found as u64, if found { "yes" } else { "no" }.to_string())
+        } else {
+            let count = linkage.count();
+            (count, count.to_string())
+        };
+        if count > 0 {
+            tileable += 1;
+        }
+        println!(
+            "{:w$}  {:>7}  {:>6}  {:>9}  {:?}",
+            name,
+            format!("{}x{}", image.width(), image.height()),
+            image.pointcount(),
+            result,
+            puzzle_start.elapsed(),
+            w = width
+        );
+    }
+    println!(
+        "{} of {} images tileable, solved in: {:?}",
+        tileable,
+        images.len(),
+        start.elapsed()
+    );
+    Ok(())
+}
+
+// Generate the images of the shape, with the given area if any
+fn sweep(
+    shape: &Shape,
+    area: Option<usize>,
+    max: Option<usize>,
+    hole: Size,
+) -> Vec<(String, Image)> {
+    let hole_area = hole.width * hole.height;
+    let mut images = Vec::new();
+    match shape {
+        Shape::Rectangles => {
+            let max = max.or(area).unwrap_or(12).min(255);
+            for height in 1..=max {
+                for width in height..=max {
+                    images.push((
+                        format!("rectangle {}x{}", width, height),
+                        Image::rectangle(width, height),
+                    ));
+                }
+            }
+        }
+        Shape::Holes => {
+            let max = max.or(area.map(|a| a + hole_area)).unwrap_or(12).min(255);
+            for height in hole.height + 2..=max {
+                for width in (hole.width + 2).max(height)..=max {
+                    images.push((
+                        format!(
+                            "rectangle {}x{} with hole {}x{}",
+                            width, height, hole.width, hole.height
+                        ),
+                        Image::with_hole(width, height, hole.width, hole.height),
+                    ));
+                }
+            }
+        }
+        Shape::Diamonds => {
+            for radius in 1..=max.unwrap_or(6).min(127) {
+                images.push((format!("diamond {}", radius), Image::diamond(radius)));
+            }
+        }
+        Shape::Staircases => {
+            for steps in 1..=max.unwrap_or(12).min(255) {
+                images.push((format!("staircase {}", steps), Image::staircase(steps)));
+            }
+        }
+    }
+    images.retain(|(_, image)| area.is_none_or(|a| image.pointcount() == a));
+    images
+}
+
+// Print what the image and the blocks are like
+fn info(puzzle: &Puzzle) -> std::io::Result<()> {
+    let (image, tiles) = load(puzzle)?;
+
+    let regions = image.regions();
+    let black = image.iter().filter(|p| p.color(2) == 0).count();
+    println!(
+        "image: {} x {} pixels, {} of them filled in {} region{}, {} black and {} white",
+        image.width(),
+        image.height(),
+        image.pointcount(),
+        regions.len(),
+        if regions.len() != 1 { "s" } else { "" },
+        black,
+        image.pointcount() - black
+    );
+    let mut labels = Vec::<(char, usize)>::new();
+    for label in (0..image.pointcount()).map(|id| image.label(id)) {
+        match labels.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, count)) => *count += 1,
+            None => labels.push((label, 1)),
+        }
+    }
+    if labels.len() > 1 {
+        let labels = labels
+            .iter()
+            .map(|(label, count)| format!("{} {}", label, count))
+            .collect::<Vec<_>>();
+        println!("pixels labelled {}", labels.join(", "));
     }
 
+    let mut area = 0;
+    println!(
+        "blocks: {} classes, {} renditions",
+        tiles.kinds_count(),
+        tiles.iter().count()
+    );
+    for class in 0..tiles.kinds_count() {
+        let renditions = tiles.iter().filter(|t| t.kind == class).collect::<Vec<_>>();
+        let size = renditions
+            .iter()
+            .map(|t| t.points.len() + 1)
+            .max()
+            .unwrap_or(0);
+        let name = tiles.name(class);
+        let glyph = tiles.glyph(class);
+        if name.chars().eq(Some(glyph)) {
+            print!("  {}: ", name);
+        } else {
+            print!("  {} (drawn as {}): ", name, glyph);
+        }
+        println!("{} pixels, {} renditions", size, renditions.len());
+        area += size;
+    }
+    if !puzzle.allow_repeat {
+        println!("all the blocks together: {} pixels", area);
+    }
+
+    let allowed = region_classes(&puzzle.pixels, &[&image], &tiles)?;
+    let linkage = constrained(&image, &tiles, puzzle.allow_repeat, &allowed);
+    println!("possible placements: {}", linkage.possible_placements());
     Ok(())
 }
+
+// Count the solutions in a single thread, saving and resuming the progress
+fn count(
+    linkage: &mut Linkage,
+    checkpoint_path: &Option<PathBuf>,
+    resume: &Option<PathBuf>,
+    interval: Duration,
+    start: Instant,
+) -> std::io::Result<()> {
+    let mut checkpoint = match resume {
+        Some(f) => Checkpoint::load(BufReader::new(File::open(f)?))?,
+        None => Checkpoint::new(),
+    };
+
+    let mut last_save = Instant::now();
+    let mut steps = 0u64; // a count may run for days, past 2^32 branches
+    let mut error = None;
+    let exhausted = linkage.count_from(&mut checkpoint, |c| {
+        steps += 1;
+        if !steps.is_multiple_of(CHECKPOINT_STEPS) || last_save.elapsed() < interval {
+            return true;
+        }
+        last_save = Instant::now();
+        match checkpoint_path {
+            Some(path) => save_checkpoint(path, c)
+                .map_err(|e| error = Some(e))
+                .is_ok(),
+            None => true,
+        }
+    })?;
+    if let Some(e) = error {
+        return Err(e);
+    }
+    if let Some(path) = checkpoint_path {
+        save_checkpoint(path, &checkpoint)?;
+    }
+
+    let len = checkpoint.count;
+    println!(
+        "{} solution{}{}, counted in: {:?}",
+        len,
+        if len != 1 { "s" } else { "" },
+        if exhausted { "" } else { " so far" },
+        start.elapsed()
+    );
+    Ok(())
+}
+
+// Write the checkpoint next to its destination first, so a crash can't corrupt it
+fn save_checkpoint(path: &Path, checkpoint: &Checkpoint) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    checkpoint.save(&mut writer)?;
+    writer.flush()?;
+    fs::rename(tmp, path)
+}
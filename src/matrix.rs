@@ -61,7 +61,7 @@ impl<T: Default + Clone> Matrix<T> {
     pub fn iter_rows<'a>(&'a self) -> MatrixRowIter<'a, T> {
         MatrixRowIter {
             current: 0,
-            matrix: &self,
+            matrix: self,
         }
     }
 }
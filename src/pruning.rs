@@ -0,0 +1,135 @@
+use crate::image::Image;
+use crate::tiles::Tiles;
+
+const NONE: u32 = u32::MAX;
+
+// Colorings of the image points and imbalances of the tiles with respect to them,
+// used to check whether the coloring of the uncovered points can still be balanced
+pub(crate) struct Parity {
+    pub colorings: Vec<Coloring>,
+}
+
+pub(crate) struct Coloring {
+    pub modulus: u8,
+    pub colors: Vec<u8>,           // color of each image point
+    pub imbalances: Vec<Vec<i32>>, // all the imbalances of tiles in each class
+}
+
+impl Parity {
+    pub fn new(image: &Image, tiles: &Tiles, moduli: &[u8]) -> Self {
+        // with less than 2 colors nothing can be unbalanced
+        let colorings = moduli
+            .iter()
+            .filter(|&&modulus| modulus >= 2)
+            .map(|&modulus| Coloring {
+                modulus,
+                colors: image.iter().map(|p| p.color(modulus)).collect(),
+                imbalances: tiles.imbalances(modulus),
+            })
+            .collect();
+        Parity { colorings }
+    }
+}
+
+// Neighbourhood of the image points and sizes of the tiles,
+// used to check whether the uncovered regions of the image can still be covered
+pub(crate) struct Regions {
+    neighbours: Vec<[u32; 4]>, // ids of the neighbouring points, NONE outside the image
+    pub sizes: Vec<Vec<i32>>,  // all the sizes of tiles in each class
+}
+
+impl Regions {
+    pub fn new(image: &Image, tiles: &Tiles) -> Self {
+        let neighbours = image
+            .iter()
+            .map(|p| {
+                let mut n = [NONE; 4];
+                for (n, &(dx, dy)) in n.iter_mut().zip([(-1, 0), (0, -1), (1, 0), (0, 1)].iter()) {
+                    if let Some(id) = image.get_point_id(p.x + dx, p.y + dy) {
+                        *n = id as u32;
+                    }
+                }
+                n
+            })
+            .collect();
+
+        let mut sizes = vec![Vec::new(); tiles.kinds_count()];
+        for tile in tiles.iter() {
+            let size = tile.points.len() as i32 + 1;
+            if !sizes[tile.kind].contains(&size) {
+                sizes[tile.kind].push(size);
+            }
+        }
+
+        Regions { neighbours, sizes }
+    }
+
+    // sizes of the connected regions formed by the points for which `uncovered` holds
+    pub fn component_sizes(&self, uncovered: impl Fn(u32) -> bool) -> Vec<u32> {
+        let mut visited = vec![false; self.neighbours.len()];
+        let mut stack = Vec::new();
+        let mut sizes = Vec::new();
+        for start in 0..self.neighbours.len() as u32 {
+            if visited[start as usize] || !uncovered(start) {
+                continue;
+            }
+            visited[start as usize] = true;
+            stack.push(start);
+            let mut size = 0;
+            while let Some(p) = stack.pop() {
+                size += 1;
+                for &n in self.neighbours[p as usize].iter() {
+                    if n != NONE && !visited[n as usize] && uncovered(n) {
+                        visited[n as usize] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        sizes
+    }
+}
+
+// Which sums in lo..=hi can be made by picking at most one value from each group,
+// or any number of values from any of the groups, if `repeat` is allowed
+// Partial sums outside of the range are dropped, the result is indexed from lo
+pub(crate) fn reachable_sums<'a>(
+    groups: impl Iterator<Item = &'a [i32]>,
+    lo: i32,
+    hi: i32,
+    repeat: bool,
+) -> Vec<bool> {
+    let mut sums = vec![false; (hi - lo + 1) as usize];
+    if lo > 0 || hi < 0 {
+        return sums;
+    }
+    sums[-lo as usize] = true;
+    let groups = groups.collect::<Vec<_>>();
+    if repeat {
+        // depth first search of the closure of {0} under adding the values
+        let mut stack = vec![0];
+        while let Some(sum) = stack.pop() {
+            for &value in groups.iter().flat_map(|g| g.iter()) {
+                let next = sum + value;
+                if lo <= next && next <= hi && !sums[(next - lo) as usize] {
+                    sums[(next - lo) as usize] = true;
+                    stack.push(next);
+                }
+            }
+        }
+    } else {
+        for group in groups {
+            let previous = sums.clone();
+            for &value in group.iter() {
+                for (sum, _) in (lo..=hi).zip(previous.iter()).filter(|&(_, &p)| p) {
+                    let next = sum + value;
+                    if lo <= next && next <= hi {
+                        sums[(next - lo) as usize] = true;
+                    }
+                }
+            }
+        }
+    }
+    sums
+}
@@ -0,0 +1,239 @@
+use crate::matrix::Matrix;
+use crate::tiles::{Placement, Point, Tiles};
+
+pub mod ansi;
+pub mod ascii;
+pub mod html;
+pub mod json;
+pub mod letters;
+pub mod png;
+pub mod svg;
+pub mod tikz;
+pub mod unicode;
+
+// A covered image: the tiles placed on it, and for every pixel the tile covering it
+pub struct Cover {
+    cells: Matrix<Option<usize>>, // index of the tile covering each pixel
+    tiles: Vec<(usize, usize)>,   // class of each tile and its index among all the tiles
+}
+
+impl Cover {
+    pub fn new(width: usize, height: usize) -> Self {
+        Cover {
+            cells: Matrix::new_fixed(width, height, None),
+            tiles: Vec::new(),
+        }
+    }
+
+    pub fn from_placements(width: usize, height: usize, placements: &[Placement]) -> Self {
+        let mut cover = Cover::new(width, height);
+        for placement in placements {
+            cover.place(placement);
+        }
+        cover
+    }
+
+    // The first rendition of every class of the tiles, side by side in a row
+    pub fn from_tiles(tiles: &Tiles) -> Self {
+        let first = (0..tiles.kinds_count())
+            .filter_map(|class| tiles.iter().position(|t| t.kind == class))
+            .collect::<Vec<_>>();
+        Cover::from_renditions(tiles, &first)
+    }
+
+    // The tiles with the given indices among all the tiles loaded, side by side in a row
+    pub fn from_renditions(tiles: &Tiles, indices: &[usize]) -> Self {
+        let all = tiles.iter().collect::<Vec<_>>();
+        let mut placements = Vec::new();
+        let (mut width, mut height) = (0, 0);
+        for &tile_index in indices {
+            let tile = all[tile_index];
+            let points = std::iter::once(Point::new(0, 0))
+                .chain(tile.points.iter().cloned())
+                .collect::<Vec<_>>();
+            let top = points.iter().map(|p| p.x).min().unwrap();
+            let left = points.iter().map(|p| p.y).min().unwrap();
+            let right = points.iter().map(|p| p.y).max().unwrap();
+            let bottom = points.iter().map(|p| p.x).max().unwrap();
+            let shift = |p: &Point| Point::new(p.x - top, p.y - left + width as i16);
+            placements.push(Placement {
+                class: tile.kind,
+                tile_index,
+                anchor: shift(&points[0]),
+                cells: points.iter().map(shift).collect(),
+            });
+            width += (right - left) as usize + 2;
+            height = height.max((bottom - top) as usize + 1);
+        }
+        Cover::from_placements(width.saturating_sub(1), height, &placements)
+    }
+
+    pub fn place(&mut self, placement: &Placement) {
+        let tile = self.tiles.len();
+        self.tiles.push((placement.class, placement.tile_index));
+        for p in placement.cells.iter() {
+            self.cells[(p.x as usize, p.y as usize)] = Some(tile);
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.height()
+    }
+
+    // #tiles placed
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn class(&self, tile: usize) -> usize {
+        self.tiles[tile].0
+    }
+
+    // Index of the tile among all the tiles loaded, i.e. which rendition of its class it is
+    pub fn index(&self, tile: usize) -> usize {
+        self.tiles[tile].1
+    }
+
+    // The tile covering the pixel on the given row and column
+    pub fn get(&self, row: usize, col: usize) -> Option<usize> {
+        if row < self.height() && col < self.width() {
+            self.cells[(row, col)]
+        } else {
+            None
+        }
+    }
+
+    // Pixels covered by the tile, as (row, column) pairs in reading order
+    pub fn cells(&self, tile: usize) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for row in 0..self.height() {
+            for col in 0..self.width() {
+                if self.cells[(row, col)] == Some(tile) {
+                    cells.push((row, col));
+                }
+            }
+        }
+        cells
+    }
+
+    // The pixel of the tile closest to its center, e.g. to put its label on
+    pub fn center(&self, tile: usize) -> (usize, usize) {
+        let cells = self.cells(tile);
+        let len = cells.len() as f64;
+        let row = cells.iter().map(|&(r, _)| r as f64).sum::<f64>() / len;
+        let col = cells.iter().map(|&(_, c)| c as f64).sum::<f64>() / len;
+        let distance =
+            |&(r, c): &(usize, usize)| (r as f64 - row).powi(2) + (c as f64 - col).powi(2);
+        cells
+            .into_iter()
+            .min_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap())
+            .unwrap_or((0, 0))
+    }
+
+    // Outline of the tile, traced along the edges of its pixels
+    // Every loop is a sequence of its corners (as row, column pairs of the grid lines),
+    // going clockwise around the tile and counterclockwise around its holes
+    pub fn outline(&self, tile: usize) -> Vec<Vec<(usize, usize)>> {
+        let same = |row: usize, col: usize, dr: isize, dc: isize| {
+            let (r, c) = (row as isize + dr, col as isize + dc);
+            r >= 0 && c >= 0 && self.get(r as usize, c as usize) == Some(tile)
+        };
+
+        // directed edges on the boundary, from their starting corner
+        let mut edges = Vec::new();
+        for (row, col) in self.cells(tile) {
+            if !same(row, col, -1, 0) {
+                edges.push(((row, col), (row, col + 1)));
+            }
+            if !same(row, col, 0, 1) {
+                edges.push(((row, col + 1), (row + 1, col + 1)));
+            }
+            if !same(row, col, 1, 0) {
+                edges.push(((row + 1, col + 1), (row + 1, col)));
+            }
+            if !same(row, col, 0, -1) {
+                edges.push(((row + 1, col), (row, col)));
+            }
+        }
+
+        let mut loops = Vec::new();
+        while let Some((start, mut next)) = edges.pop() {
+            let mut corners = vec![start];
+            while next != start {
+                let i = edges
+                    .iter()
+                    .position(|&(from, _)| from == next)
+                    .expect("The outline of a tile is not closed!");
+                corners.push(next);
+                next = edges.swap_remove(i).1;
+            }
+            loops.push(simplify(corners));
+        }
+        loops
+    }
+}
+
+// leave out the corners where the outline goes straight on
+fn simplify(corners: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let len = corners.len();
+    (0..len)
+        .filter(|&i| {
+            let (a, b, c) = (
+                corners[(i + len - 1) % len],
+                corners[i],
+                corners[(i + 1) % len],
+            );
+            !(a.0 == b.0 && b.0 == c.0 || a.1 == b.1 && b.1 == c.1)
+        })
+        .map(|i| corners[i])
+        .collect()
+}
+
+// A color for the tiles of the given class, as red, green and blue components
+// Hues of the consecutive classes are spread by the golden angle, so they are easy to tell apart
+pub fn color(class: usize) -> [u8; 3] {
+    let hue = (class as f64 * 137.508) % 360.0 / 60.0;
+    let (saturation, value) = (0.55, 0.95);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let c = |v: f64| ((v + m) * 255.0).round() as u8;
+    [c(r), c(g), c(b)]
+}
+
+// The color in the #rrggbb notation
+pub fn hex_color(class: usize) -> String {
+    let [r, g, b] = color(class);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// Escape the characters with a special meaning in XML (and HTML)
+pub fn escape(s: &str) -> String {
+    s.chars()
+        .fold(String::with_capacity(s.len()), |mut acc, c| {
+            match c {
+                '<' => acc.push_str("&lt;"),
+                '>' => acc.push_str("&gt;"),
+                '&' => acc.push_str("&amp;"),
+                '"' => acc.push_str("&quot;"),
+                c => acc.push(c),
+            }
+            acc
+        })
+}
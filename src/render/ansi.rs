@@ -0,0 +1,77 @@
+use crate::render::{color, Cover};
+use crate::tiles::Tiles;
+
+// Colors supported by the terminal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    Xterm256,  // the 6x6x6 color cube of the 256 color terminals
+    TrueColor, // 24-bit colors
+}
+
+impl Palette {
+    // Guess the palette from the environment of the program
+    pub fn detect() -> Self {
+        match std::env::var("COLORTERM") {
+            Ok(ref c) if c == "truecolor" || c == "24bit" => Palette::TrueColor,
+            _ => Palette::Xterm256,
+        }
+    }
+
+    // escape sequence setting the background to the color
+    fn background(self, [r, g, b]: [u8; 3]) -> String {
+        match self {
+            Palette::Xterm256 => {
+                let level = |v: u8| (v as u16 * 5 + 127) / 255;
+                let index = 16 + 36 * level(r) + 6 * level(g) + level(b);
+                format!("\x1b[48;5;{}m", index)
+            }
+            Palette::TrueColor => format!("\x1b[48;2;{};{};{}m", r, g, b),
+        }
+    }
+}
+
+// Paint the covered image for a terminal, every pixel as two characters
+// with the background of the color of its tile's class and the glyph of the class
+// Tiles of the same class are painted in different shades of its color, so that
+// they can be told apart even if they are neighbours
+pub fn show(cover: &Cover, tiles: &Tiles, palette: Palette) -> String {
+    let mut placed = vec![0; tiles.kinds_count()];
+    let colors = (0..cover.len())
+        .map(|t| {
+            let class = cover.class(t);
+            let shade = [4, 3, 2][placed[class] % 3];
+            placed[class] += 1;
+            color(class).map(|c| (c as u16 * shade / 4) as u8)
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = String::new();
+    for row in 0..cover.height() {
+        let mut current = None;
+        for col in 0..cover.width() {
+            let tile = cover.get(row, col);
+            if tile != current {
+                match tile {
+                    Some(t) => {
+                        output += &palette.background(colors[t]);
+                        output += "\x1b[30m";
+                    }
+                    None => output += "\x1b[0m",
+                }
+                current = tile;
+            }
+            match tile {
+                Some(t) => {
+                    output.push(tiles.glyph(cover.class(t)));
+                    output.push(' ');
+                }
+                None => output += "  ",
+            }
+        }
+        if current.is_some() {
+            output += "\x1b[0m";
+        }
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,39 @@
+use crate::render::Cover;
+use crate::tiles::Tiles;
+
+// Draw the covered image with ASCII characters, every pixel as the glyph of the class
+// of the tile covering it, the tiles bordered by `|`, `-` and `+`
+pub fn show(cover: &Cover, tiles: &Tiles) -> String {
+    let (width, height) = (cover.width(), cover.height());
+    let mut canvas = vec![vec![' '; 2 * width + 1]; 2 * height + 1];
+    let tile = |row: usize, col: usize, dr: usize, dc: usize| {
+        if row < dr || col < dc {
+            None
+        } else {
+            cover.get(row - dr, col - dc)
+        }
+    };
+
+    for row in 0..=height {
+        for col in 0..=width {
+            if let Some(t) = tile(row, col, 0, 0) {
+                canvas[2 * row + 1][2 * col + 1] = tiles.glyph(cover.class(t));
+            }
+            // the edges above and to the left of the pixel
+            if col < width && tile(row, col, 1, 0) != tile(row, col, 0, 0) {
+                canvas[2 * row][2 * col + 1] = '-';
+                canvas[2 * row][2 * col] = '+';
+                canvas[2 * row][2 * col + 2] = '+';
+            }
+            if row < height && tile(row, col, 0, 1) != tile(row, col, 0, 0) {
+                canvas[2 * row + 1][2 * col] = '|';
+                canvas[2 * row][2 * col] = '+';
+                canvas[2 * row + 2][2 * col] = '+';
+            }
+        }
+    }
+
+    canvas.iter().fold(String::new(), |acc, line| {
+        format!("{}{}\n", acc, line.iter().collect::<String>())
+    })
+}
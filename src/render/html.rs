@@ -0,0 +1,72 @@
+use crate::render::{escape, svg, Cover};
+use crate::tiles::Tiles;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+nav { margin-bottom: 1em; }
+.gallery { display: flex; flex-wrap: wrap; gap: 1.5em; }
+figure { margin: 0; padding: 0.5em; text-align: center; }
+figure:target { outline: 3px solid #4080ff; }
+figcaption a { color: inherit; text-decoration: none; }
+table { border-collapse: collapse; }
+th, td { padding: 0.3em 0.8em; text-align: center; vertical-align: bottom; }
+th { vertical-align: middle; }
+";
+
+// A self-contained HTML page with all the covered images as inline SVG thumbnails,
+// the classes of tiles and how many times every rendition of them is used
+// `count` is the number of solutions found, of which `covers` may only be the first ones
+pub fn show(covers: &[Cover], tiles: &Tiles, count: usize, cell_size: usize) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html += &format!(
+        "<title>{} solution{}</title>\n<style>\n{}</style>\n</head>\n<body>\n",
+        count,
+        if count != 1 { "s" } else { "" },
+        STYLE
+    );
+    html += &format!(
+        "<h1>{} solution{}</h1>\n",
+        count,
+        if count != 1 { "s" } else { "" }
+    );
+    html += "<nav><a href=\"#tiles\">Tiles</a> | <a href=\"#renditions\">Renditions used</a> \
+             | <a href=\"#solutions\">Solutions</a></nav>\n";
+
+    html += "<h2 id=\"tiles\">Tiles</h2>\n";
+    html += &svg::show(&Cover::from_tiles(tiles), tiles, cell_size, true);
+
+    // #uses of every tile loaded, over the solutions shown
+    let mut uses = vec![0; tiles.iter().count()];
+    for cover in covers {
+        for t in 0..cover.len() {
+            uses[cover.index(t)] += 1;
+        }
+    }
+    html += "<h2 id=\"renditions\">Renditions used</h2>\n<table>\n";
+    for class in 0..tiles.kinds_count() {
+        html += &format!("<tr><th>{}</th>", escape(tiles.name(class)));
+        for (tile_index, _) in tiles.iter().enumerate().filter(|(_, t)| t.kind == class) {
+            let thumbnail = Cover::from_renditions(tiles, &[tile_index]);
+            html += &format!(
+                "<td>{}<br>{}</td>",
+                svg::show(&thumbnail, tiles, (cell_size / 2).max(2), false),
+                uses[tile_index]
+            );
+        }
+        html += "</tr>\n";
+    }
+    html += "</table>\n";
+
+    html += "<h2 id=\"solutions\">Solutions</h2>\n";
+    if covers.len() < count {
+        html += &format!("<p>Showing the first {} of them.</p>\n", covers.len());
+    }
+    html += "<div class=\"gallery\">\n";
+    for (n, cover) in covers.iter().enumerate() {
+        html += &format!(
+            "<figure id=\"solution-{0}\">\n{1}<figcaption><a href=\"#solution-{0}\">#{0}</a></figcaption>\n</figure>\n",
+            n + 1,
+            svg::show(cover, tiles, cell_size, false)
+        );
+    }
+    html + "</div>\n</body>\n</html>\n"
+}
@@ -0,0 +1,299 @@
+use std::io::{self, Read};
+use std::iter::Peekable;
+use std::str::Chars;
+use std::time::Duration;
+
+use crate::checkpoint::invalid;
+use crate::tiles::{Placement, Point, Tiles};
+
+// Describe the solutions in JSON, together with the data about the search
+// Every tile is described by the name of its class, the index of its rendition
+// among all the tiles loaded and the pixels covered by it, as [row, column] pairs,
+// starting with the anchor
+pub fn show(
+    solutions: &[Vec<Placement>],
+    tiles: &Tiles,
+    count: usize,
+    time: Duration,
+    exhaustive: bool,
+) -> String {
+    let mut json = String::from("{\n  \"solutions\": [");
+    for (n, placements) in solutions.iter().enumerate() {
+        json += if n == 0 { "\n    [" } else { ",\n    [" };
+        for (i, placement) in placements.iter().enumerate() {
+            let cells = placement
+                .cells
+                .iter()
+                .map(|p| format!("[{}, {}]", p.x, p.y))
+                .collect::<Vec<_>>();
+            json += &format!(
+                "{}\n      {{\"class\": {}, \"orientation\": {}, \"cells\": [{}]}}",
+                if i == 0 { "" } else { "," },
+                string(tiles.name(placement.class)),
+                placement.tile_index,
+                cells.join(", ")
+            );
+        }
+        json += "\n    ]";
+    }
+    json += if solutions.is_empty() {
+        "],\n"
+    } else {
+        "\n  ],\n"
+    };
+    json += &format!(
+        "  \"count\": {},\n  \"time\": {},\n  \"exhaustive\": {}\n}}\n",
+        count,
+        time.as_secs_f64(),
+        exhaustive
+    );
+    json
+}
+
+// A JSON string literal
+pub fn string(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            c if (c as u32) < 0x20 => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+// Read the solutions back from a JSON document written by `show`
+// Only the classes and the pixels of the tiles are needed, the renditions are optional
+pub fn parse(mut reader: impl Read, tiles: &Tiles) -> io::Result<Vec<Vec<Placement>>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut chars = text.chars().peekable();
+    let document = Value::parse(&mut chars, 0)?;
+    skip_whitespace(&mut chars);
+    if chars.next().is_some() {
+        return Err(invalid("trailing characters after JSON".to_string()));
+    }
+
+    let solutions = match document.get("solutions") {
+        Some(Value::Array(solutions)) => solutions,
+        _ => return Err(invalid("no array of solutions in JSON".to_string())),
+    };
+    solutions
+        .iter()
+        .map(|solution| match solution {
+            Value::Array(placements) => placements.iter().map(|p| placement(p, tiles)).collect(),
+            _ => Err(invalid("a solution is not an array of tiles".to_string())),
+        })
+        .collect()
+}
+
+fn placement(value: &Value, tiles: &Tiles) -> io::Result<Placement> {
+    let class = match value.get("class") {
+        Some(Value::String(name)) => tiles
+            .kind(name)
+            .ok_or_else(|| invalid(format!("unknown tile class `{}`", name)))?,
+        _ => return Err(invalid("a tile without a class".to_string())),
+    };
+    let tile_index = match value.get("orientation") {
+        Some(&Value::Number(n)) => integer(n, usize::MAX)
+            .filter(|&i| tiles.iter().nth(i).is_some_and(|t| t.kind == class))
+            .ok_or_else(|| {
+                invalid(format!(
+                    "orientation {} is not a rendition of class `{}`",
+                    n,
+                    tiles.name(class)
+                ))
+            })?,
+        _ => tiles.iter().position(|t| t.kind == class).unwrap_or(0),
+    };
+    let cells = match value.get("cells") {
+        Some(Value::Array(cells)) => cells
+            .iter()
+            .map(|c| match c {
+                Value::Array(c) => match c[..] {
+                    [Value::Number(x), Value::Number(y)] => {
+                        match (integer(x, 255), integer(y, 255)) {
+                            (Some(x), Some(y)) => Ok(Point::new(x as i16, y as i16)),
+                            _ => Err(invalid(format!("a pixel [{}, {}] out of the image", x, y))),
+                        }
+                    }
+                    _ => Err(invalid("a pixel is not a pair of numbers".to_string())),
+                },
+                _ => Err(invalid("a pixel is not a pair of numbers".to_string())),
+            })
+            .collect::<io::Result<Vec<_>>>()?,
+        _ => return Err(invalid("a tile without pixels".to_string())),
+    };
+    let anchor = *cells
+        .first()
+        .ok_or_else(|| invalid("a tile without pixels".to_string()))?;
+    Ok(Placement {
+        class,
+        tile_index,
+        anchor,
+        cells,
+    })
+}
+
+// The number if it is a whole number from 0 to max
+fn integer(n: f64, max: usize) -> Option<usize> {
+    if n.fract() == 0.0 && n >= 0.0 && n <= max as f64 {
+        Some(n as usize)
+    } else {
+        None
+    }
+}
+
+// The deepest nesting of arrays and objects read, the solutions need just a few levels
+const MAX_DEPTH: usize = 32;
+
+// a JSON value, just as much as needed to read the solutions
+enum Value {
+    Literal, // null, true or false
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn parse(chars: &mut Peekable<Chars>, depth: usize) -> io::Result<Self> {
+        skip_whitespace(chars);
+        if depth > MAX_DEPTH {
+            return Err(invalid("too deeply nested JSON".to_string()));
+        }
+        match chars.peek() {
+            Some('{') => {
+                chars.next();
+                let mut members = Vec::new();
+                for_each_item(chars, '}', |chars| {
+                    skip_whitespace(chars);
+                    let key = parse_string(chars)?;
+                    skip_whitespace(chars);
+                    expect(chars, ':')?;
+                    members.push((key, Value::parse(chars, depth + 1)?));
+                    Ok(())
+                })?;
+                Ok(Value::Object(members))
+            }
+            Some('[') => {
+                chars.next();
+                let mut items = Vec::new();
+                for_each_item(chars, ']', |chars| {
+                    items.push(Value::parse(chars, depth + 1)?);
+                    Ok(())
+                })?;
+                Ok(Value::Array(items))
+            }
+            Some('"') => Ok(Value::String(parse_string(chars)?)),
+            Some(&c) if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() || "+-.eE".contains(c) {
+                        number.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                number
+                    .parse()
+                    .map(Value::Number)
+                    .map_err(|_| invalid(format!("invalid number `{}` in JSON", number)))
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_alphabetic() {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                match &word[..] {
+                    "null" | "true" | "false" => Ok(Value::Literal),
+                    _ => Err(invalid(format!("unexpected `{}` in JSON", word))),
+                }
+            }
+            None => Err(invalid("unexpected end of JSON".to_string())),
+        }
+    }
+}
+
+// parse the comma separated items of an array or an object, up to the closing character
+fn for_each_item(
+    chars: &mut Peekable<Chars>,
+    close: char,
+    mut item: impl FnMut(&mut Peekable<Chars>) -> io::Result<()>,
+) -> io::Result<()> {
+    skip_whitespace(chars);
+    if chars.peek() == Some(&close) {
+        chars.next();
+        return Ok(());
+    }
+    loop {
+        item(chars)?;
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => (),
+            Some(c) if c == close => return Ok(()),
+            _ => return Err(invalid(format!("expected `,` or `{}` in JSON", close))),
+        }
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> io::Result<String> {
+    expect(chars, '"')?;
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('u') => {
+                    let code = chars.by_ref().take(4).collect::<String>();
+                    let c = u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(std::char::from_u32)
+                        .ok_or_else(|| invalid(format!("invalid escape `\\u{}`", code)))?;
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+                None => break,
+            },
+            Some(c) => s.push(c),
+            None => break,
+        }
+    }
+    Err(invalid("unterminated string in JSON".to_string()))
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> io::Result<()> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        _ => Err(invalid(format!("expected `{}` in JSON", expected))),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
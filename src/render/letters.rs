@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+
+use crate::checkpoint::invalid;
+use crate::render::Cover;
+use crate::tiles::{Placement, Point, Tiles};
+
+const EMPTY: char = '.';
+
+// Write the covered image as a grid of letters, every pixel as the glyph of the class
+// of the tile covering it and the pixels not covered as dots
+pub fn show(cover: &Cover, tiles: &Tiles) -> String {
+    let mut output = String::with_capacity((cover.width() + 1) * cover.height());
+    for row in 0..cover.height() {
+        for col in 0..cover.width() {
+            output.push(
+                cover
+                    .get(row, col)
+                    .map_or(EMPTY, |t| tiles.glyph(cover.class(t))),
+            );
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// Read a grid of letters back into the tiles placed, finding the rendition of every tile
+// Neighbouring pixels with the same letter are split into as few tiles as necessary
+pub fn parse(reader: impl BufRead, tiles: &Tiles) -> io::Result<Vec<Placement>> {
+    let mut letters = Vec::new();
+    for (x, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            break;
+        }
+        for (y, c) in line.chars().enumerate() {
+            if c != EMPTY && !c.is_whitespace() {
+                let class = tiles
+                    .kind_of_glyph(c)
+                    .ok_or_else(|| invalid(format!("unknown tile class `{}`", c)))?;
+                letters.push((Point::new(x as i16, y as i16), class));
+            }
+        }
+    }
+
+    let mut placements = Vec::new();
+    let mut unvisited = letters.iter().cloned().collect::<HashSet<_>>();
+    for &(start, class) in letters.iter() {
+        if !unvisited.remove(&(start, class)) {
+            continue;
+        }
+        // the region of the pixels with the same letter
+        let mut region = vec![start];
+        let mut i = 0;
+        while i < region.len() {
+            for n in region[i].neighbours().iter() {
+                if unvisited.remove(&(*n, class)) {
+                    region.push(*n);
+                }
+            }
+            i += 1;
+        }
+        region.sort_by_key(|p| (p.x, p.y));
+
+        let start = placements.len();
+        if !split(&region, class, tiles, &mut placements) {
+            return Err(invalid(format!(
+                "the pixels at [{}, {}] and around can't be covered by tiles `{}`",
+                region[0].x,
+                region[0].y,
+                tiles.name(class)
+            )));
+        }
+        placements[start..].reverse();
+    }
+    Ok(placements)
+}
+
+// Read all the grids of letters, separated by blank lines
+pub fn parse_all(reader: impl BufRead, tiles: &Tiles) -> io::Result<Vec<Vec<Placement>>> {
+    let mut solutions = Vec::new();
+    let mut grid = String::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            if !grid.is_empty() {
+                solutions.push(parse(grid.as_bytes(), tiles)?);
+                grid.clear();
+            }
+        } else {
+            grid += &line;
+            grid.push('\n');
+        }
+    }
+    if !grid.is_empty() {
+        solutions.push(parse(grid.as_bytes(), tiles)?);
+    }
+    Ok(solutions)
+}
+
+// split the region (sorted in the reading order) into tiles of the class, by backtracking
+// the placements are pushed in the reverse order
+fn split(region: &[Point], class: usize, tiles: &Tiles, placements: &mut Vec<Placement>) -> bool {
+    let first = match region.first() {
+        Some(&p) => p,
+        None => return true,
+    };
+    for (tile_index, tile) in tiles.iter().enumerate().filter(|(_, t)| t.kind == class) {
+        // the first pixel of the tile in the reading order must cover the first one of the region
+        let origin = Point::new(0, 0);
+        let top = tile
+            .points
+            .iter()
+            .cloned()
+            .chain(std::iter::once(origin))
+            .min_by_key(|p| (p.x, p.y))
+            .unwrap();
+        let anchor = Point::new(first.x - top.x, first.y - top.y);
+        let cells = std::iter::once(anchor)
+            .chain(
+                tile.points
+                    .iter()
+                    .map(|p| Point::new(anchor.x + p.x, anchor.y + p.y)),
+            )
+            .collect::<Vec<_>>();
+        if !cells.iter().all(|c| {
+            region
+                .binary_search_by_key(&(c.x, c.y), |p| (p.x, p.y))
+                .is_ok()
+        }) {
+            continue;
+        }
+
+        let rest = region
+            .iter()
+            .cloned()
+            .filter(|p| !cells.contains(p))
+            .collect::<Vec<_>>();
+        if split(&rest, class, tiles, placements) {
+            placements.push(Placement {
+                class,
+                tile_index,
+                anchor,
+                cells,
+            });
+            return true;
+        }
+    }
+    false
+}
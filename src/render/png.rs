@@ -0,0 +1,84 @@
+use crate::render::{color, Cover};
+use crate::zlib;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const BORDER: [u8; 3] = [0, 0, 0];
+
+// Draw the covered image as a PNG image, every tile filled with the color of its class
+// Every pixel of the image is a square of `cell_size` pixels, tiles are bordered by black
+// lines and the pixels inside them by lines of a darker shade of their color
+pub fn show(cover: &Cover, cell_size: usize) -> Vec<u8> {
+    let border = (cell_size / 10).max(1);
+    let width = cover.width() * cell_size + border;
+    let height = cover.height() * cell_size + border;
+    let mut raster = Vec::with_capacity(width * height);
+
+    // the tiles covering the pixels around the given point of the grid,
+    // or only the pixels on both sides of the given grid line
+    let get = |row: usize, col: usize| {
+        if row == 0 || col == 0 {
+            None
+        } else {
+            cover.get(row - 1, col - 1)
+        }
+    };
+    let shade = |tile: usize| {
+        let [r, g, b] = color(cover.class(tile));
+        [r / 4 * 3, g / 4 * 3, b / 4 * 3]
+    };
+    let line = |tiles: &[Option<usize>]| match tiles[0] {
+        Some(t) if tiles.iter().all(|&s| s == tiles[0]) => shade(t),
+        _ if tiles.iter().any(Option::is_some) => BORDER,
+        _ => BACKGROUND,
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let (row, col) = (y / cell_size + 1, x / cell_size + 1);
+            let pixel = match (y % cell_size < border, x % cell_size < border) {
+                (true, true) => line(&[
+                    get(row - 1, col - 1),
+                    get(row - 1, col),
+                    get(row, col - 1),
+                    get(row, col),
+                ]),
+                (true, false) => line(&[get(row - 1, col), get(row, col)]),
+                (false, true) => line(&[get(row, col - 1), get(row, col)]),
+                (false, false) => get(row, col).map_or(BACKGROUND, |t| color(cover.class(t))),
+            };
+            raster.push(pixel);
+        }
+    }
+
+    encode(width, height, &raster)
+}
+
+// Encode the rows of RGB pixels as a PNG image
+pub fn encode(width: usize, height: usize, raster: &[[u8; 3]]) -> Vec<u8> {
+    let mut scanlines = Vec::with_capacity((3 * width + 1) * height);
+    for row in raster.chunks(width) {
+        scanlines.push(0); // no filter
+        scanlines.extend(row.iter().flat_map(|p| p.iter()));
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, default compression, filtering and no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib::compress(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = zlib::crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
@@ -0,0 +1,62 @@
+use crate::render::{escape, hex_color, Cover};
+use crate::tiles::Tiles;
+
+// Draw the covered image in SVG, every tile as a polygon filled with the color of its class
+// Every pixel is a square with the side of `cell_size` units,
+// tiles are optionally labelled by the names of their classes
+pub fn show(cover: &Cover, tiles: &Tiles, cell_size: usize, labels: bool) -> String {
+    let stroke = (cell_size / 10).max(1);
+    let width = cover.width() * cell_size + 2 * stroke;
+    let height = cover.height() * cell_size + 2 * stroke;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        width, height
+    );
+    svg += &format!(
+        "<g stroke=\"black\" stroke-width=\"{}\" stroke-linejoin=\"round\">\n",
+        stroke
+    );
+    for tile in 0..cover.len() {
+        svg += &format!(
+            "<path d=\"{}\" fill=\"{}\"/>\n",
+            path(cover, tile, cell_size, stroke),
+            hex_color(cover.class(tile))
+        );
+    }
+    svg += "</g>\n";
+
+    if labels {
+        svg += &format!(
+            "<g font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">\n",
+            cell_size / 2
+        );
+        for tile in 0..cover.len() {
+            let (row, col) = cover.center(tile);
+            svg += &format!(
+                "<text x=\"{}\" y=\"{}\">{}</text>\n",
+                stroke + col * cell_size + cell_size / 2,
+                stroke + row * cell_size + cell_size / 2,
+                escape(tiles.name(cover.class(tile)))
+            );
+        }
+        svg += "</g>\n";
+    }
+    svg + "</svg>\n"
+}
+
+// The outline of the tile as SVG path data, shifted by the offset
+pub fn path(cover: &Cover, tile: usize, cell_size: usize, offset: usize) -> String {
+    let mut d = String::new();
+    for corners in cover.outline(tile) {
+        for (i, &(row, col)) in corners.iter().enumerate() {
+            d += &format!(
+                "{}{} {} ",
+                if i == 0 { "M" } else { "L" },
+                offset + col * cell_size,
+                offset + row * cell_size
+            );
+        }
+        d.push('Z');
+    }
+    d
+}
@@ -0,0 +1,85 @@
+use crate::render::{color, Cover};
+use crate::tiles::Tiles;
+
+// Draw the covered image as a TikZ picture, to be included in LaTeX documents
+// Every tile is filled with the color of its class and outlined, its pixels being squares
+// with the side of `cell_size` points; tiles are optionally labelled by the names of their classes
+// The colors are defined inside the picture, which only needs the tikz package
+pub fn show(cover: &Cover, tiles: &Tiles, cell_size: usize, labels: bool) -> String {
+    let mut tikz = format!(
+        "\\begin{{tikzpicture}}[x={0}pt, y=-{0}pt, line width={1}pt, line join=round]\n",
+        cell_size,
+        cell_size as f64 / 10.0
+    );
+    let mut classes = (0..cover.len()).map(|t| cover.class(t)).collect::<Vec<_>>();
+    classes.sort_unstable();
+    classes.dedup();
+    for class in classes {
+        let [r, g, b] = color(class);
+        tikz += &format!(
+            "\\definecolor{{class{}}}{{RGB}}{{{},{},{}}}\n",
+            class, r, g, b
+        );
+    }
+
+    for tile in 0..cover.len() {
+        let path = path(cover, tile);
+        tikz += &format!(
+            "\\fill[class{}, even odd rule] {};\n\\draw {};\n",
+            cover.class(tile),
+            path,
+            path
+        );
+    }
+    if labels {
+        for tile in 0..cover.len() {
+            let (row, col) = cover.center(tile);
+            tikz += &format!(
+                "\\node at ({}.5, {}.5) {{{}}};\n",
+                col,
+                row,
+                escape(tiles.name(cover.class(tile)))
+            );
+        }
+    }
+    tikz + "\\end{tikzpicture}\n"
+}
+
+// Draw the first rendition of every class of the tiles, labelled by its name
+pub fn show_tiles(tiles: &Tiles, cell_size: usize) -> String {
+    show(&Cover::from_tiles(tiles), tiles, cell_size, true)
+}
+
+// The outline of the tile as a TikZ path, in the grid coordinates
+fn path(cover: &Cover, tile: usize) -> String {
+    cover
+        .outline(tile)
+        .iter()
+        .map(|corners| {
+            let corners = corners
+                .iter()
+                .map(|&(row, col)| format!("({}, {})", col, row))
+                .collect::<Vec<_>>();
+            corners.join(" -- ") + " -- cycle"
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Escape the characters with a special meaning in LaTeX
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,73 @@
+use crate::matrix::Matrix;
+use crate::render::Cover;
+use crate::tiles::Tiles;
+
+// Box drawing characters by the weights of the lines going up, right, down and left
+// from the middle of the character (0 none, 1 light, 2 heavy), in base 3
+const JUNCTIONS: &str =
+    " ╴╸╷┐┑╻┒┓╶─╾┌┬┭┎┰┱╺╼━┍┮┯┏┲┳╵┘┙│┤┥╽┧┪└┴┵├┼┽┟╁╅┕┶┷┝┾┿┢╆╈╹┚┛╿┦┩┃┨┫┖┸┹┞╀╃┠╂╉┗┺┻┡╄╇┣╊╋";
+
+// Draw the covered image with the box drawing characters, like ascii::show,
+// with heavy lines on the borders of the tiles and light lines between their pixels
+pub fn show(cover: &Cover, tiles: &Tiles) -> String {
+    let (width, height) = (cover.width(), cover.height());
+    let junctions = JUNCTIONS.chars().collect::<Vec<_>>();
+    let junction = |up: usize, right: usize, down: usize, left: usize| {
+        junctions[27 * up + 9 * right + 3 * down + left]
+    };
+
+    // weight of the line between the pixels, given in the coordinates of the canvas
+    let get = |x: usize, y: usize| {
+        if x % 2 == 1 && y % 2 == 1 {
+            cover.get(x / 2, y / 2)
+        } else {
+            None
+        }
+    };
+    let weight = |a: Option<usize>, b: Option<usize>| match (a, b) {
+        (None, None) => 0,
+        (a, b) if a == b => 1,
+        _ => 2,
+    };
+    let horizontal = |x: usize, y: usize| {
+        if x <= 2 * height && y % 2 == 1 {
+            weight(if x > 0 { get(x - 1, y) } else { None }, get(x + 1, y))
+        } else {
+            0
+        }
+    };
+    let vertical = |x: usize, y: usize| {
+        if y <= 2 * width && x % 2 == 1 {
+            weight(if y > 0 { get(x, y - 1) } else { None }, get(x, y + 1))
+        } else {
+            0
+        }
+    };
+
+    let mut canvas = Matrix::new_fixed(2 * width + 1, 2 * height + 1, ' ');
+    for x in 0..=2 * height {
+        for y in 0..=2 * width {
+            canvas[(x, y)] = match (x % 2, y % 2) {
+                (1, 1) => get(x, y).map_or(' ', |t| tiles.glyph(cover.class(t))),
+                (0, 1) => {
+                    let w = horizontal(x, y);
+                    junction(0, w, 0, w)
+                }
+                (1, 0) => {
+                    let w = vertical(x, y);
+                    junction(w, 0, w, 0)
+                }
+                _ => junction(
+                    if x > 0 { vertical(x - 1, y) } else { 0 },
+                    horizontal(x, y + 1),
+                    vertical(x + 1, y),
+                    if y > 0 { horizontal(x, y - 1) } else { 0 },
+                ),
+            }
+        }
+    }
+
+    canvas.iter_rows().fold("".to_string(), |acc, slice| {
+        format!("{}{}\n", acc, slice.iter().collect::<String>())
+    })
+}
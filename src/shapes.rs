@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use crate::tiles::Point;
+
+// Which transformations of a shape give the same tile
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symmetry {
+    Free,     // rotations and reflections
+    OneSided, // rotations only
+    Fixed,    // none
+}
+
+impl std::str::FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "free" => Ok(Symmetry::Free),
+            "one-sided" => Ok(Symmetry::OneSided),
+            "fixed" => Ok(Symmetry::Fixed),
+            _ => Err(format!("unknown symmetry `{}`", s)),
+        }
+    }
+}
+
+// The shape moved to the smallest non-negative coordinates, its pixels in the reading order
+pub fn normalize(cells: &[Point]) -> Vec<Point> {
+    let cells = cells.iter().map(|&p| (p, ())).collect::<Vec<_>>();
+    normalize_colored(&cells)
+        .into_iter()
+        .map(|(p, _)| p)
+        .collect()
+}
+
+fn normalize_colored<T: Copy + Ord>(cells: &[(Point, T)]) -> Vec<(Point, T)> {
+    let top = cells.iter().map(|(p, _)| p.x).min().unwrap_or(0);
+    let left = cells.iter().map(|(p, _)| p.y).min().unwrap_or(0);
+    let mut cells = cells
+        .iter()
+        .map(|&(p, color)| (Point::new(p.x - top, p.y - left), color))
+        .collect::<Vec<_>>();
+    cells.sort_unstable();
+    cells
+}
+
+// The distinct renditions of the shape (normalized) under the symmetry, the shape itself first
+pub fn orientations(cells: &[Point], symmetry: Symmetry) -> Vec<Vec<Point>> {
+    let cells = cells.iter().map(|&p| (p, ())).collect::<Vec<_>>();
+    colored_orientations(&cells, symmetry)
+        .into_iter()
+        .map(|shape| shape.into_iter().map(|(p, _)| p).collect())
+        .collect()
+}
+
+// Like orientations, the pixels carrying their colors along
+// Renditions of the same shape with differently colored pixels are distinct
+pub fn colored_orientations<T: Copy + Ord>(
+    cells: &[(Point, T)],
+    symmetry: Symmetry,
+) -> Vec<Vec<(Point, T)>> {
+    let mut transformed = cells.to_vec();
+    let mut orientations = Vec::new();
+    let reflections = if symmetry == Symmetry::Free { 2 } else { 1 };
+    for _ in 0..reflections {
+        for _ in 0..4 {
+            let shape = normalize_colored(&transformed);
+            if !orientations.contains(&shape) {
+                orientations.push(shape);
+            }
+            if symmetry == Symmetry::Fixed {
+                return orientations;
+            }
+            // rotate by the right angle
+            for (p, _) in transformed.iter_mut() {
+                *p = Point::new(p.y, -p.x);
+            }
+        }
+        // reflect along the vertical axis
+        for (p, _) in transformed.iter_mut() {
+            p.y = -p.y;
+        }
+    }
+    orientations
+}
+
+// The rendition of the shape representing all of them under the symmetry
+pub fn canonical(cells: &[Point], symmetry: Symmetry) -> Vec<Point> {
+    orientations(cells, symmetry)
+        .into_iter()
+        .min()
+        .unwrap_or_default()
+}
+
+// All the polyominoes with the given number of pixels, distinct under the symmetry
+// Every one of them is given by its canonical rendition, sorted
+pub fn polyominoes(size: usize, symmetry: Symmetry) -> Vec<Vec<Point>> {
+    if size == 0 {
+        return Vec::new();
+    }
+    let mut shapes = vec![vec![Point::new(0, 0)]];
+    for _ in 1..size {
+        let mut grown = HashSet::new();
+        for shape in shapes.iter() {
+            for p in shape.iter() {
+                for n in p.neighbours().iter().filter(|n| !shape.contains(n)) {
+                    let mut cells = shape.clone();
+                    cells.push(*n);
+                    grown.insert(canonical(&cells, symmetry));
+                }
+            }
+        }
+        shapes = grown.into_iter().collect();
+    }
+    shapes.sort_unstable();
+    shapes
+}
@@ -1,5 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
 
+use crate::shapes::{colored_orientations, orientations, polyominoes, Symmetry};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Point {
     pub x: i16,
     pub y: i16,
@@ -8,43 +12,328 @@ pub struct Point {
 pub struct Tile {
     pub kind: usize,
     pub points: Vec<Point>,
+    // colors of the origin and of the points, the pixels without a color can cover any pixel
+    // of the image, otherwise only the pixels labelled by their color; empty if none has a color
+    pub colors: Vec<Option<char>>,
 }
 
+// The character of a pixel without a color, in the lists of colors and in the pictures of tiles
+const UNCOLORED: char = '#';
+
+// The largest number of pixels of a tile, whose size is kept in a u8
+const MAX_TILE_SIZE: usize = 255;
+
+// A tile placed onto the image
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    pub class: usize,
+    pub tile_index: usize, // index of the tile among all the tiles loaded
+    pub anchor: Point,     // the point of the image the origin of the tile is placed onto
+    pub cells: Vec<Point>, // the points of the image covered by the tile, the anchor first
+}
+
+// The sets of tiles in the tiles directory, compiled into the library
+const BUILTIN: [(&str, &str); 10] = [
+    ("domino", include_str!("../tiles/domino")),
+    ("hexomino", include_str!("../tiles/hexomino")),
+    ("int_ext", include_str!("../tiles/int_ext")),
+    ("monomino", include_str!("../tiles/monomino")),
+    ("pentomino", include_str!("../tiles/pentomino")),
+    (
+        "pentomino_square",
+        include_str!("../tiles/pentomino_square"),
+    ),
+    ("soma_planar", include_str!("../tiles/soma_planar")),
+    ("tetromino", include_str!("../tiles/tetromino")),
+    (
+        "tetromino_one_sided",
+        include_str!("../tiles/tetromino_one_sided"),
+    ),
+    ("tromino", include_str!("../tiles/tromino")),
+];
+
 pub struct Tiles {
-    kinds: Vec<char>,
+    kinds: Vec<String>, // names of the classes
+    glyphs: Vec<char>,  // characters drawing the classes in the text formats
     data: Vec<Tile>,
 }
 
+impl Point {
+    pub fn new(x: i16, y: i16) -> Self {
+        Point { x, y }
+    }
+
+    // the points sharing an edge with this one
+    pub fn neighbours(&self) -> [Point; 4] {
+        [
+            Point::new(self.x - 1, self.y),
+            Point::new(self.x, self.y - 1),
+            Point::new(self.x + 1, self.y),
+            Point::new(self.x, self.y + 1),
+        ]
+    }
+
+    // color of the point in the coloring of the plane by diagonals (x + y) mod `modulus`
+    pub fn color(&self, modulus: u8) -> u8 {
+        (self.x + self.y).rem_euclid(modulus as i16) as u8
+    }
+}
+
 impl Tile {
-    fn parse(s: &str, used: &mut HashMap<char, usize>, lookup: &mut Vec<char>) -> Option<Self> {
-        let mut words = s.split_whitespace();
-        let identifier = words.next()?.chars().next()?;
-        let kind = *used.entry(identifier).or_insert_with(|| {
-            let new_kind = lookup.len();
-            lookup.push(identifier);
-            new_kind
-        });
+    fn parse(s: &str, used: &mut HashMap<String, usize>, lookup: &mut Vec<String>) -> Option<Self> {
+        let mut words = s.split_whitespace().peekable();
+        let identifier = words.next()?;
+        // the colors of the pixels optionally follow, like `:b#w`
+        let colors = match words.next_if(|w| w.starts_with(':')) {
+            Some(colors) => colors[1..].chars().map(color).collect(),
+            None => Vec::new(),
+        };
         let points = words
             .filter_map(|w| w.parse().ok())
             .collect::<Vec<_>>()
             .chunks_exact(2)
-            .take(254)
+            .take(MAX_TILE_SIZE - 1)
             .map(|c| Point { x: c[0], y: c[1] })
+            .collect::<Vec<_>>();
+        if !colors.is_empty() && colors.len() != points.len() + 1 {
+            return None;
+        }
+        let kind = class(identifier, used, lookup);
+        Some(Tile {
+            kind,
+            points,
+            colors,
+        })
+    }
+}
+
+fn color(c: char) -> Option<char> {
+    Some(c).filter(|&c| c != UNCOLORED)
+}
+
+// The class with the given name, a new one when it is used for the first time
+fn class(identifier: &str, used: &mut HashMap<String, usize>, lookup: &mut Vec<String>) -> usize {
+    *used.entry(identifier.to_string()).or_insert_with(|| {
+        let new_kind = lookup.len();
+        lookup.push(identifier.to_string());
+        new_kind
+    })
+}
+
+// Letters, digits and then other alphabetic characters, to name or draw the classes
+fn letters() -> impl Iterator<Item = char> {
+    ('A'..='Z')
+        .chain('a'..='z')
+        .chain('0'..='9')
+        .chain(('\u{c0}'..).filter(|c| c.is_alphabetic()))
+}
+
+// The character drawing every class: the name of the class if it is a single character,
+// otherwise its first character, or the first letter not drawing any other class
+fn glyphs(kinds: &[String]) -> Vec<char> {
+    let single = |name: &str| {
+        let mut chars = name.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    };
+    let mut used = kinds
+        .iter()
+        .filter_map(|k| single(k))
+        .collect::<HashSet<_>>();
+    let mut free = letters();
+    kinds
+        .iter()
+        .map(|k| match single(k) {
+            Some(glyph) => glyph,
+            None => {
+                let glyph = k
+                    .chars()
+                    .next()
+                    .filter(|c| !used.contains(c))
+                    .unwrap_or_else(|| free.find(|c| !used.contains(c)).unwrap());
+                used.insert(glyph);
+                glyph
+            }
+        })
+        .collect()
+}
+
+// The header of a tile drawn as a picture, `[F]` optionally followed by the symmetry
+// generating the other renditions of the tile, otherwise it is used just as drawn,
+// and by `colored` when the characters of the picture are the colors of its pixels
+fn picture_header(line: &str) -> Option<(&str, Symmetry, bool)> {
+    let (identifier, rest) = line.trim().strip_prefix('[')?.split_once(']')?;
+    if identifier.is_empty() || identifier.contains(char::is_whitespace) {
+        return None;
+    }
+    // the symmetry and `colored`, in any order
+    let (mut symmetry, mut colored) = (None, false);
+    for word in rest.split_whitespace() {
+        match word {
+            "colored" if !colored => colored = true,
+            _ if symmetry.is_none() => symmetry = Some(word.parse().ok()?),
+            _ => return None,
+        }
+    }
+    Some((identifier, symmetry.unwrap_or(Symmetry::Fixed), colored))
+}
+
+impl Tile {
+    // The tile covering the given pixels, with its origin at the top pixel of the leftmost column
+    // and the other pixels column by column, as they are written in the tile files
+    pub fn from_cells(kind: usize, cells: &[Point]) -> Self {
+        let cells = cells.iter().map(|&p| (p, None)).collect::<Vec<_>>();
+        Tile::from_colored_cells(kind, &cells)
+    }
+
+    // The tile covering the given pixels of the given colors
+    pub fn from_colored_cells(kind: usize, cells: &[(Point, Option<char>)]) -> Self {
+        let mut cells = cells.to_vec();
+        cells.sort_unstable_by_key(|(p, _)| (p.y, p.x));
+        let origin = cells[0].0;
+        let points = cells[1..]
+            .iter()
+            .map(|(p, _)| Point::new(p.x - origin.x, p.y - origin.y))
             .collect();
-        Some(Tile { kind, points })
+        let colors = if cells.iter().any(|(_, color)| color.is_some()) {
+            cells.iter().map(|&(_, color)| color).collect()
+        } else {
+            Vec::new()
+        };
+        Tile {
+            kind,
+            points,
+            colors,
+        }
+    }
+
+    // Imbalances of the tile in the coloring by diagonals, for every position of its origin
+    // The imbalance is the #points of color 0 times `modulus`, minus the #points,
+    // so e.g. for the checkerboard coloring, it is the difference of #black and #white points
+    pub fn imbalances(&self, modulus: u8) -> Vec<i32> {
+        let size = self.points.len() as i32 + 1;
+        let mut imbalances = Vec::with_capacity(modulus as usize);
+        for shift in 0..modulus as i16 {
+            let origin = (shift == 0) as i32;
+            let zeros = self
+                .points
+                .iter()
+                .filter(|p| {
+                    Point {
+                        x: p.x + shift,
+                        y: p.y,
+                    }
+                    .color(modulus)
+                        == 0
+                })
+                .count() as i32;
+            let imbalance = modulus as i32 * (zeros + origin) - size;
+            if !imbalances.contains(&imbalance) {
+                imbalances.push(imbalance);
+            }
+        }
+        imbalances
     }
 }
 
 impl Tiles {
+    // Every line is either a tile given by the coordinates of its pixels, or the header
+    // of a tile drawn on the following lines, up to an empty line, by `#` characters
+    // or by the characters of the colors of its pixels
     pub fn load(reader: impl std::io::BufRead) -> Self {
         let mut used = HashMap::new();
         let mut kinds = Vec::new();
-        let data = reader
-            .lines()
-            .filter_map(Result::ok)
-            .filter_map(|s| Tile::parse(&s, &mut used, &mut kinds))
-            .collect();
-        Tiles { kinds, data }
+        let mut data = Vec::new();
+        let mut lines = reader.lines().map_while(Result::ok).peekable();
+        while let Some(line) = lines.next() {
+            let (identifier, symmetry, colored) = match picture_header(&line) {
+                Some(header) => header,
+                None => {
+                    data.extend(Tile::parse(&line, &mut used, &mut kinds));
+                    continue;
+                }
+            };
+            let mut cells = Vec::new();
+            let mut row = 0;
+            while let Some(line) =
+                lines.next_if(|l| !l.trim().is_empty() && picture_header(l).is_none())
+            {
+                // in a colored picture, any other character than `.` or whitespace is a pixel
+                let pixels = line.chars().enumerate().filter(|&(_, c)| match colored {
+                    true => c != '.' && !c.is_whitespace(),
+                    false => c == UNCOLORED,
+                });
+                cells.extend(pixels.map(|(column, c)| (Point::new(row, column as i16), color(c))));
+                row += 1;
+            }
+            // a picture too large for a tile is skipped, like an invalid line
+            if cells.is_empty() || cells.len() > MAX_TILE_SIZE {
+                continue;
+            }
+            let kind = class(identifier, &mut used, &mut kinds);
+            for cells in colored_orientations(&cells, symmetry) {
+                data.push(Tile::from_colored_cells(kind, &cells));
+            }
+        }
+        Tiles::new(kinds, data)
+    }
+
+    fn new(kinds: Vec<String>, data: Vec<Tile>) -> Self {
+        let glyphs = glyphs(&kinds);
+        Tiles {
+            kinds,
+            glyphs,
+            data,
+        }
+    }
+
+    // One of the sets of tiles built into the library, see Tiles::builtin_names
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN
+            .iter()
+            .find(|&&(n, _)| n == name)
+            .map(|&(_, data)| Tiles::load(data.as_bytes()))
+    }
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|&(name, _)| name)
+    }
+
+    // All the polyominoes with the given number of pixels, every class of them
+    // with all its renditions under the symmetry
+    // The classes are named by letters, digits and then by other alphabetic characters
+    pub fn generate(size: usize, symmetry: Symmetry) -> Self {
+        let mut names = letters();
+        let mut kinds = Vec::new();
+        let mut data = Vec::new();
+        for shape in polyominoes(size, symmetry) {
+            let kind = kinds.len();
+            kinds.push(
+                names
+                    .next()
+                    .expect("Out of names for the tiles!")
+                    .to_string(),
+            );
+            for cells in orientations(&shape, symmetry) {
+                data.push(Tile::from_cells(kind, &cells));
+            }
+        }
+        Tiles::new(kinds, data)
+    }
+
+    // Write the tiles in the format they are loaded in
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        for tile in self.data.iter() {
+            write!(writer, "{}", self.kinds[tile.kind])?;
+            if !tile.colors.is_empty() {
+                let colors = tile.colors.iter().map(|c| c.unwrap_or(UNCOLORED));
+                write!(writer, " :{}", colors.collect::<String>())?;
+            }
+            for p in tile.points.iter() {
+                write!(writer, " {} {}", p.x, p.y)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
     }
 
     pub fn kinds_count(&self) -> usize {
@@ -55,7 +344,34 @@ impl Tiles {
         self.data.iter()
     }
 
-    pub fn name(&self, kind: usize) -> char {
-        self.kinds[kind]
+    // All the imbalances of tiles in every class, see Tile::imbalances
+    pub fn imbalances(&self, modulus: u8) -> Vec<Vec<i32>> {
+        let mut imbalances = vec![Vec::new(); self.kinds.len()];
+        for tile in self.data.iter() {
+            for i in tile.imbalances(modulus) {
+                if !imbalances[tile.kind].contains(&i) {
+                    imbalances[tile.kind].push(i);
+                }
+            }
+        }
+        imbalances
+    }
+
+    // The class with the given name
+    pub fn kind(&self, name: &str) -> Option<usize> {
+        self.kinds.iter().position(|k| k == name)
+    }
+
+    // The class drawn by the given character
+    pub fn kind_of_glyph(&self, glyph: char) -> Option<usize> {
+        self.glyphs.iter().position(|&g| g == glyph)
+    }
+
+    pub fn name(&self, kind: usize) -> &str {
+        &self.kinds[kind]
+    }
+
+    pub fn glyph(&self, kind: usize) -> char {
+        self.glyphs[kind]
     }
 }
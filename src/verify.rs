@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::image::Image;
+use crate::tiles::{Placement, Point, Tiles};
+
+// The first problem found with a claimed solution
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    UnknownClass(usize), // a tile of a class that was not loaded
+    WrongShape(usize),   // a tile (by its index in the solution) of no rendition of its class
+    Repeated(String),    // a class used more than once, with the repetition not allowed
+    OutsideImage(Point), // a pixel covered, but not filled in the image
+    Overlap(Point),      // a pixel covered by more than one tile
+    Uncovered(Point),    // a filled pixel not covered by any tile
+    WrongColors(usize),  // a tile (by its index) with its colors on pixels of other colors
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownClass(class) => write!(f, "unknown tile class {}", class),
+            Error::WrongShape(t) => write!(f, "tile {} has a shape not found in its class", t),
+            Error::Repeated(name) => write!(f, "tile class `{}` is used repeatedly", name),
+            Error::OutsideImage(p) => write!(f, "pixel [{}, {}] is outside the image", p.x, p.y),
+            Error::Overlap(p) => write!(f, "pixel [{}, {}] is covered repeatedly", p.x, p.y),
+            Error::Uncovered(p) => write!(f, "pixel [{}, {}] is not covered", p.x, p.y),
+            Error::WrongColors(t) => write!(f, "tile {} lies on pixels of other colors", t),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Check independently of the search that the tiles placed form a solution:
+// every one of them is a rendition of its class, the classes are not repeated unless allowed,
+// every filled pixel of the image is covered exactly once and the colors of the tiles match
+// The renditions claimed by the placements are ignored, only their pixels matter
+pub fn verify(
+    image: &Image,
+    tiles: &Tiles,
+    allow_repeat: bool,
+    placements: &[Placement],
+) -> Result<(), Error> {
+    // the shapes of every class with the colors of their pixels, translated to start at the origin
+    let mut shapes = vec![Vec::new(); tiles.kinds_count()];
+    for tile in tiles.iter() {
+        let cells = std::iter::once(Point::new(0, 0))
+            .chain(tile.points.iter().cloned())
+            .zip(tile.colors.iter().cloned().chain(std::iter::repeat(None)))
+            .collect::<Vec<_>>();
+        shapes[tile.kind].push(normalize(&cells));
+    }
+
+    let mut used = vec![false; tiles.kinds_count()];
+    let mut covered = HashMap::new();
+    for (t, placement) in placements.iter().enumerate() {
+        let class = placement.class;
+        if class >= shapes.len() {
+            return Err(Error::UnknownClass(class));
+        }
+        // the pixels covered with their labels in the image
+        let cells = placement
+            .cells
+            .iter()
+            .map(|&p| (p, image.get_point_id(p.x, p.y).map(|id| image.label(id))))
+            .collect::<Vec<_>>();
+        let cells = normalize(&cells);
+        let same_shape = |shape: &[(Point, Option<char>)]| {
+            shape.len() == cells.len() && shape.iter().zip(cells.iter()).all(|(s, c)| s.0 == c.0)
+        };
+        if !shapes[class].iter().any(|shape| same_shape(shape)) {
+            return Err(Error::WrongShape(t));
+        }
+        if used[class] && !allow_repeat {
+            return Err(Error::Repeated(tiles.name(class).to_string()));
+        }
+        used[class] = true;
+
+        for &p in placement.cells.iter() {
+            if image.get_point_id(p.x, p.y).is_none() {
+                return Err(Error::OutsideImage(p));
+            }
+            if covered.insert(p, t).is_some() {
+                return Err(Error::Overlap(p));
+            }
+        }
+        let same_colors = |shape: &[(Point, Option<char>)]| {
+            (shape.iter().zip(cells.iter()))
+                .all(|(s, c)| s.1.is_none_or(|color| c.1 == Some(color)))
+        };
+        if !shapes[class]
+            .iter()
+            .any(|shape| same_shape(shape) && same_colors(shape))
+        {
+            return Err(Error::WrongColors(t));
+        }
+    }
+
+    match image.iter().find(|p| !covered.contains_key(p)) {
+        Some(&p) => Err(Error::Uncovered(p)),
+        None => Ok(()),
+    }
+}
+
+// the pixels in the reading order, translated so that the first one is the origin
+fn normalize<T: Copy>(cells: &[(Point, T)]) -> Vec<(Point, T)> {
+    let mut cells = cells.to_vec();
+    cells.sort_by_key(|(p, _)| (p.x, p.y));
+    let first = cells.first().map_or(Point::new(0, 0), |&(p, _)| p);
+    cells
+        .iter()
+        .map(|&(p, color)| (Point::new(p.x - first.x, p.y - first.y), color))
+        .collect()
+}
@@ -0,0 +1,404 @@
+// A minimal implementation of the zlib format (RFC 1950) with deflate compression (RFC 1951),
+// using only the fixed Huffman codes and a simple greedy search for repeated strings,
+// and with decompression of any deflate stream
+
+use std::io;
+
+use crate::checkpoint::invalid;
+
+const WINDOW: usize = 32768;
+const MAX_CHAIN: usize = 64; // max #earlier positions tried when looking for a match
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// Writes bits into bytes, starting with the least significant bit
+struct BitWriter {
+    data: Vec<u8>,
+    buffer: u32,
+    len: u8,
+}
+
+impl BitWriter {
+    fn new(data: Vec<u8>) -> Self {
+        BitWriter {
+            data,
+            buffer: 0,
+            len: 0,
+        }
+    }
+
+    fn write(&mut self, bits: u32, count: u8) {
+        self.buffer |= bits << self.len;
+        self.len += count;
+        while self.len >= 8 {
+            self.data.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    // Huffman codes are stored starting with the most significant bit
+    fn write_code(&mut self, code: u32, count: u8) {
+        let reversed = code.reverse_bits() >> (32 - count);
+        self.write(reversed, count);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.data.push(self.buffer as u8);
+        }
+        self.data
+    }
+}
+
+// Compress the data into the zlib format
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    // 32K window, no dictionary, fastest compression level
+    let mut writer = BitWriter::new(vec![0x78, 0x01]);
+    writer.write(1, 1); // the last block
+    writer.write(1, 2); // compressed with the fixed Huffman codes
+
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut previous = vec![usize::MAX; data.len()];
+    let hash = |i: usize| {
+        (((data[i] as usize) << 10) ^ ((data[i + 1] as usize) << 5) ^ data[i + 2] as usize) & 0x7fff
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let (mut length, mut distance) = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let max = (data.len() - i).min(MAX_MATCH);
+                let len = (0..max)
+                    .take_while(|&k| data[candidate + k] == data[i + k])
+                    .count();
+                if len > length {
+                    length = len;
+                    distance = i - candidate;
+                }
+                candidate = previous[candidate];
+                chain += 1;
+            }
+        }
+
+        let step = if length >= MIN_MATCH {
+            write_length(&mut writer, length);
+            write_distance(&mut writer, distance);
+            length
+        } else {
+            write_literal(&mut writer, data[i] as u16);
+            1
+        };
+        let end = (i + step).min(data.len().saturating_sub(MIN_MATCH - 1));
+        for (j, previous) in previous.iter_mut().enumerate().take(end).skip(i) {
+            let h = hash(j);
+            *previous = head[h];
+            head[h] = j;
+        }
+        i += step;
+    }
+    write_literal(&mut writer, 256); // end of the block
+
+    let mut result = writer.finish();
+    result.extend_from_slice(&adler32(data).to_be_bytes());
+    result
+}
+
+fn write_literal(writer: &mut BitWriter, value: u16) {
+    let value = value as u32;
+    match value {
+        0..=143 => writer.write_code(0x30 + value, 8),
+        144..=255 => writer.write_code(0x190 + value - 144, 9),
+        256..=279 => writer.write_code(value - 256, 7),
+        _ => writer.write_code(0xc0 + value - 280, 8),
+    }
+}
+
+fn write_length(writer: &mut BitWriter, length: usize) {
+    let code = LENGTH_BASE
+        .iter()
+        .rposition(|&b| b as usize <= length)
+        .unwrap();
+    write_literal(writer, 257 + code as u16);
+    let extra = (length - LENGTH_BASE[code] as usize) as u32;
+    writer.write(extra, LENGTH_EXTRA[code]);
+}
+
+fn write_distance(writer: &mut BitWriter, distance: usize) {
+    let code = DISTANCE_BASE
+        .iter()
+        .rposition(|&b| b as usize <= distance)
+        .unwrap();
+    writer.write_code(code as u32, 5);
+    let extra = (distance - DISTANCE_BASE[code] as usize) as u32;
+    writer.write(extra, DISTANCE_EXTRA[code]);
+}
+
+// Reads bits from bytes, starting with the least significant bit
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize, // in bits
+}
+
+impl<'a> BitReader<'a> {
+    fn read(&mut self, count: u8) -> io::Result<u32> {
+        let mut bits = 0;
+        for i in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or_else(|| invalid("unexpected end of compressed data".to_string()))?;
+            bits |= ((byte >> (self.position % 8)) as u32 & 1) << i;
+            self.position += 1;
+        }
+        Ok(bits)
+    }
+
+    // skip to the next byte boundary and return its index
+    fn align(&mut self) -> usize {
+        self.position = self.position.div_ceil(8) * 8;
+        self.position / 8
+    }
+}
+
+// A canonical Huffman code, given by the number of codes of every length
+// and the symbols in the order of their codes
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    // Huffman codes are stored starting with the most significant bit
+    fn decode(&self, reader: &mut BitReader) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in self.counts[1..].iter() {
+            code |= reader.read(1)? as u16;
+            if code < first + count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid(
+            "invalid Huffman code in compressed data".to_string(),
+        ))
+    }
+}
+
+// Decompress data in the zlib format, into at most limit bytes
+pub fn decompress(data: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    if data.len() < 6
+        || data[0] & 0x0f != 8
+        || !(data[0] as u16 * 256 + data[1] as u16).is_multiple_of(31)
+    {
+        return Err(invalid("invalid zlib header".to_string()));
+    }
+    if data[1] & 0x20 != 0 {
+        return Err(invalid("zlib data with a preset dictionary".to_string()));
+    }
+    let mut reader = BitReader {
+        data: &data[2..],
+        position: 0,
+    };
+    let mut output = Vec::new();
+    loop {
+        let last = reader.read(1)? == 1;
+        match reader.read(2)? {
+            0 => {
+                let start = reader.align();
+                let stored = reader.data.get(start..start + 4);
+                let (length, complement) = match stored {
+                    Some(&[a, b, c, d]) => (u16::from_le_bytes([a, b]), u16::from_le_bytes([c, d])),
+                    _ => return Err(invalid("unexpected end of compressed data".to_string())),
+                };
+                if length != !complement {
+                    return Err(invalid("invalid length of a stored block".to_string()));
+                }
+                let block = reader
+                    .data
+                    .get(start + 4..start + 4 + length as usize)
+                    .ok_or_else(|| invalid("unexpected end of compressed data".to_string()))?;
+                if output.len() + block.len() > limit {
+                    return Err(too_long());
+                }
+                output.extend_from_slice(block);
+                reader.position = (start + 4 + length as usize) * 8;
+            }
+            1 => {
+                let mut lengths = [8; 288];
+                lengths[144..256].iter_mut().for_each(|l| *l = 9);
+                lengths[256..280].iter_mut().for_each(|l| *l = 7);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate(&mut reader, &literals, &distances, &mut output, limit)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate(&mut reader, &literals, &distances, &mut output, limit)?;
+            }
+            _ => return Err(invalid("invalid type of a compressed block".to_string())),
+        }
+        if last {
+            break;
+        }
+    }
+    let end = reader.align();
+    match reader.data.get(end..end + 4) {
+        Some(&[a, b, c, d]) if u32::from_be_bytes([a, b, c, d]) == adler32(&output) => Ok(output),
+        _ => Err(invalid("wrong checksum of compressed data".to_string())),
+    }
+}
+
+// Read the codes of a block compressed with dynamic Huffman codes
+fn dynamic_codes(reader: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+    let literal_count = reader.read(5)? as usize + 257;
+    let distance_count = reader.read(5)? as usize + 1;
+    let code_count = reader.read(4)? as usize + 4;
+    let mut code_lengths = [0; 19];
+    for &i in ORDER.iter().take(code_count) {
+        code_lengths[i] = reader.read(3)? as u8;
+    }
+    let code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code.decode(reader)? {
+            16 => match lengths.last() {
+                Some(&previous) => (previous, 3 + reader.read(2)?),
+                None => return Err(invalid("invalid lengths of Huffman codes".to_string())),
+            },
+            17 => (0, 3 + reader.read(3)?),
+            18 => (0, 11 + reader.read(7)?),
+            length => (length as u8, 1),
+        };
+        lengths.extend((0..repeat).map(|_| length));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(invalid("invalid lengths of Huffman codes".to_string()));
+    }
+    let (literals, distances) = lengths.split_at(literal_count);
+    Ok((Huffman::new(literals), Huffman::new(distances)))
+}
+
+// Decode the literals and the repeated strings of a compressed block
+fn inflate(
+    reader: &mut BitReader,
+    literals: &Huffman,
+    distances: &Huffman,
+    output: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        if symbol < 256 && output.len() >= limit {
+            return Err(too_long());
+        } else if symbol < 256 {
+            output.push(symbol as u8);
+            continue;
+        } else if symbol == 256 {
+            return Ok(());
+        }
+        let code = symbol - 257;
+        if code >= LENGTH_BASE.len() {
+            return Err(invalid("invalid length in compressed data".to_string()));
+        }
+        let length = LENGTH_BASE[code] as usize + reader.read(LENGTH_EXTRA[code])? as usize;
+        let code = distances.decode(reader)? as usize;
+        if code >= DISTANCE_BASE.len() {
+            return Err(invalid("invalid distance in compressed data".to_string()));
+        }
+        let distance = DISTANCE_BASE[code] as usize + reader.read(DISTANCE_EXTRA[code])? as usize;
+        if distance > output.len() {
+            return Err(invalid(
+                "distance too far back in compressed data".to_string(),
+            ));
+        }
+        if output.len() + length > limit {
+            return Err(too_long());
+        }
+        let start = output.len() - distance;
+        for i in 0..length {
+            output.push(output[start + i]);
+        }
+    }
+}
+
+fn too_long() -> io::Error {
+    invalid("more decompressed data than expected".to_string())
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+// CRC-32 as used by PNG and many others
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0, |c, &byte| {
+        table[((c ^ byte as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
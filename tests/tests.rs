@@ -1,9 +1,16 @@
-use polyomino::tiles::Tiles;
+use polyomino::bitmap::Bitmap;
+use polyomino::checkpoint::Checkpoint;
+use polyomino::shapes::{self, Symmetry};
+use polyomino::tiles::{Placement, Point, Tiles};
 use polyomino::image::Image;
 use polyomino::linkage::Linkage;
+use polyomino::render::ansi::{self, Palette};
+use polyomino::render::{ascii, html, json, letters, png, svg, tikz, unicode, Cover};
+use polyomino::verify::{self, verify};
 
 use std::fs::File;
 use std::io::BufReader;
+use std::time::Duration;
 
 #[test]
 fn pentomino_chess() {
@@ -102,3 +109,878 @@ fn find_one() {
     let solutions = Linkage::build(&image, &tiles, false).solve(false);
     assert_eq!(solutions.len(), 1);
 }
+
+#[test]
+fn count_resume() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let mut uninterrupted = Checkpoint::new();
+    assert!(linkage.count_from(&mut uninterrupted, |_| true).unwrap());
+    assert_eq!(uninterrupted.count, 200);
+
+    // interrupt the search every 50 branches and resume it from a saved checkpoint
+    let mut saved = Vec::new();
+    Checkpoint::new().save(&mut saved).unwrap();
+    loop {
+        let mut checkpoint = Checkpoint::load(&saved[..]).unwrap();
+        let mut steps = 0;
+        let mut linkage = Linkage::build(&image, &tiles, false);
+        let exhausted = linkage
+            .count_from(&mut checkpoint, |_| {
+                steps += 1;
+                steps <= 50
+            })
+            .unwrap();
+        saved.clear();
+        checkpoint.save(&mut saved).unwrap();
+        if exhausted {
+            break;
+        }
+    }
+    assert_eq!(Checkpoint::load(&saved[..]).unwrap(), uninterrupted);
+}
+
+#[test]
+fn split_jobs() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let jobs = linkage.prefixes(3);
+    assert!(jobs.len() > 1);
+    let count: u64 = jobs.iter().map(|j| linkage.count_prefix(j).unwrap()).sum();
+    assert_eq!(count, 200);
+
+    // at depth 0 the only job is the whole search, taking no branches
+    assert_eq!(linkage.prefixes(0), vec![Vec::<u32>::new()]);
+    assert_eq!(linkage.count_prefix(&[]).unwrap(), 200);
+}
+
+#[test]
+fn solution_order() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let parallel = Linkage::build(&image, &tiles, false).solve(true);
+    for depth in 0..4 {
+        let mut linkage = Linkage::build(&image, &tiles, false);
+        linkage.set_parallel_depth(depth);
+        assert_eq!(linkage.solve(true), parallel);
+    }
+}
+
+#[test]
+fn region_pruning() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect20x3").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    linkage.enable_region_pruning(&image, &tiles);
+    let solutions = linkage.solve(true);
+    assert_eq!(solutions.len(), 8);
+}
+
+#[test]
+fn parity_pruning() {
+    let f = File::open("tiles/domino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/mutilated").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, true);
+    linkage.enable_parity_pruning(&image, &tiles, &[2]);
+    let solutions = linkage.solve(true);
+    assert_eq!(solutions.len(), 0);
+
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    linkage.enable_parity_pruning(&image, &tiles, &[2, 3, 0, 1]);
+    let solutions = linkage.solve(true);
+    assert_eq!(solutions.len(), 200);
+
+    // the imbalances of the monominoes reach up to (modulus - 1) times their count
+    let tiles = Tiles::builtin("monomino").unwrap();
+    for image in [Image::rectangle(1, 1), Image::rectangle(3, 2)].iter() {
+        let unpruned = Linkage::build(image, &tiles, true).count();
+        for &modulus in [3, 4].iter() {
+            let mut linkage = Linkage::build(image, &tiles, true);
+            linkage.enable_parity_pruning(image, &tiles, &[modulus]);
+            assert_eq!(linkage.count(), unpruned);
+        }
+    }
+}
+
+#[test]
+fn svg_output() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let solutions = linkage.solve(false);
+    let cover = linkage.cover(solutions.front().unwrap(), &image);
+    assert_eq!(cover.len(), 4);
+    for tile in 0..cover.len() {
+        assert_eq!(cover.cells(tile).len(), 5);
+        assert_eq!(cover.outline(tile).len(), 1);
+    }
+
+    let output = svg::show(&cover, &tiles, 10, true);
+    assert!(output.starts_with("<svg"));
+    assert!(output.contains("width=\"52\" height=\"42\""));
+    assert_eq!(output.matches("<path").count(), 4);
+    assert_eq!(output.matches("<text").count(), 4);
+}
+
+#[test]
+fn png_output() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let solutions = linkage.solve(false);
+    let cover = linkage.cover(solutions.front().unwrap(), &image);
+    let output = png::show(&cover, 10);
+    assert_eq!(&output[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&output[12..16], b"IHDR");
+    assert_eq!(&output[16..24], &[0, 0, 0, 51, 0, 0, 0, 41]);
+    assert_eq!(&output[output.len() - 8..output.len() - 4], b"IEND");
+}
+
+#[test]
+fn json_output() {
+    let f = File::open("tiles/tromino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect3x1").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let solutions = linkage.solve(true);
+    let placements = solutions
+        .iter()
+        .map(|s| linkage.placements(s, &image))
+        .collect::<Vec<_>>();
+    let output = json::show(&placements, &tiles, solutions.len(), Duration::from_millis(1500), true);
+    assert_eq!(
+        output,
+        r#"{
+  "solutions": [
+    [
+      {"class": "I", "orientation": 0, "cells": [[0, 0], [0, 1], [0, 2]]}
+    ]
+  ],
+  "count": 1,
+  "time": 1.5,
+  "exhaustive": true
+}
+"#
+    );
+}
+
+#[test]
+fn placements() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let tiles = tiles.iter().collect::<Vec<_>>();
+    for solution in linkage.solve(true).iter() {
+        let placements = linkage.placements(solution, &image);
+        assert_eq!(placements.len(), 4);
+        for Placement { class, tile_index, anchor, cells } in placements {
+            let tile = tiles[tile_index];
+            assert_eq!(tile.kind, class);
+            assert_eq!(cells[0], anchor);
+            let points = tile.points.iter().map(|p| Point {
+                x: anchor.x + p.x,
+                y: anchor.y + p.y,
+            });
+            assert_eq!(cells[1..], points.collect::<Vec<_>>()[..]);
+        }
+    }
+}
+
+#[test]
+fn ansi_output() {
+    let f = File::open("tiles/domino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect3x2").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, true);
+    let solutions = linkage.solve(true);
+    let cover = linkage.cover(solutions.front().unwrap(), &image);
+    let output = ansi::show(&cover, &tiles, Palette::TrueColor);
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        assert!(line.starts_with("\x1b[48;2;"));
+        assert!(line.ends_with("\x1b[0m"));
+    }
+    // the three dominoes are painted in different shades
+    let mut shades = output
+        .split("\x1b[48;2;")
+        .skip(1)
+        .map(|s| s.split('m').next().unwrap())
+        .collect::<Vec<_>>();
+    shades.sort();
+    shades.dedup();
+    assert_eq!(shades.len(), 3);
+}
+
+#[test]
+fn unicode_output() {
+    let f = File::open("tiles/tromino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect3x2").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, true);
+    let solutions = linkage.solve(true);
+    let cover = linkage.cover(solutions.front().unwrap(), &image);
+    assert_eq!(
+        unicode::show(&cover, &tiles),
+        "┏━┯━┯━┓\n┃I│I│I┃\n┣━┿━┿━┫\n┃I│I│I┃\n┗━┷━┷━┛\n"
+    );
+}
+
+#[test]
+fn letters_roundtrip() {
+    let sorted = |mut placements: Vec<Placement>| {
+        placements.sort_by_key(|p| (p.anchor.x, p.anchor.y));
+        placements
+    };
+
+    for &(tilefile, imagefile, repeat) in [
+        ("tiles/pentomino", "images/rect5x4", false),
+        ("tiles/domino", "images/rect3x2", true),
+    ]
+    .iter()
+    {
+        let f = File::open(tilefile).unwrap();
+        let f = BufReader::new(f);
+        let tiles = Tiles::load(f);
+
+        let i = File::open(imagefile).unwrap();
+        let i = BufReader::new(i);
+        let image = Image::load(i, 'x');
+
+        let mut linkage = Linkage::build(&image, &tiles, repeat);
+        for solution in linkage.solve(true).iter() {
+            let grid = letters::show(&linkage.cover(solution, &image), &tiles);
+            let parsed = letters::parse(grid.as_bytes(), &tiles).unwrap();
+            let cover = Cover::from_placements(image.width(), image.height(), &parsed);
+            assert_eq!(letters::show(&cover, &tiles), grid);
+            // neighbouring tiles of the same class could be split differently
+            if !repeat {
+                assert_eq!(sorted(parsed), sorted(linkage.placements(solution, &image)));
+            }
+        }
+    }
+
+    let f = File::open("tiles/pentomino").unwrap();
+    let tiles = Tiles::load(BufReader::new(f));
+    assert!(letters::parse(&b"IIIII\nLLLL.\n"[..], &tiles).is_err());
+    assert!(letters::parse(&b"IIIIQ\n"[..], &tiles).is_err());
+}
+
+#[test]
+fn verify_solutions() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let solutions = linkage.solve(true);
+    let placements = solutions
+        .iter()
+        .map(|s| linkage.placements(s, &image))
+        .collect::<Vec<_>>();
+    for p in placements.iter() {
+        assert_eq!(verify(&image, &tiles, false, p), Ok(()));
+    }
+
+    // the solutions read back from the output formats
+    let output = json::show(&placements, &tiles, placements.len(), Duration::from_secs(1), true);
+    let parsed = json::parse(output.as_bytes(), &tiles).unwrap();
+    assert_eq!(parsed, placements);
+    // renditions of other classes or out of the tiles, pixels out of the image
+    for tile in [
+        r#"{"class": "I", "orientation": 99999, "cells": [[0, 0]]}"#,
+        r#"{"class": "I", "orientation": 8, "cells": [[0, 0]]}"#,
+        r#"{"class": "I", "orientation": 0.5, "cells": [[0, 0]]}"#,
+        r#"{"class": "I", "cells": [[-1, 0]]}"#,
+        r#"{"class": "I", "cells": [[0, 256]]}"#,
+        r#"{"class": "I", "cells": [[0, 1.5]]}"#,
+    ]
+    .iter()
+    {
+        let document = format!(r#"{{"solutions": [[{}]]}}"#, tile);
+        assert!(json::parse(document.as_bytes(), &tiles).is_err());
+    }
+    let nested = format!(r#"{{"solutions": {}"#, "[".repeat(200000));
+    assert!(json::parse(nested.as_bytes(), &tiles).is_err());
+    let grids = solutions
+        .iter()
+        .map(|s| letters::show(&linkage.cover(s, &image), &tiles))
+        .collect::<Vec<_>>();
+    let parsed = letters::parse_all(grids.join("\n").as_bytes(), &tiles).unwrap();
+    assert_eq!(parsed.len(), solutions.len());
+    for p in parsed.iter() {
+        assert_eq!(verify(&image, &tiles, false, p), Ok(()));
+    }
+
+    let mut p = placements[0].clone();
+    p.pop();
+    assert!(matches!(verify(&image, &tiles, false, &p), Err(verify::Error::Uncovered(_))));
+    let mut p = placements[0].clone();
+    p[1].cells[0] = p[0].cells[0];
+    assert_eq!(verify(&image, &tiles, false, &p), Err(verify::Error::WrongShape(1)));
+    let mut p = placements[0].clone();
+    p[1].class = p[0].class;
+    assert!(verify(&image, &tiles, false, &p).is_err());
+    let mut p = placements[0].clone();
+    for c in p[0].cells.iter_mut() {
+        c.y += 5;
+    }
+    assert!(verify(&image, &tiles, false, &p).is_err());
+    let grid = "IIIII\nIIIII\nIIIII\nIIIII\n";
+    let p = letters::parse(grid.as_bytes(), &tiles).unwrap();
+    assert_eq!(verify(&image, &tiles, false, &p), Err(verify::Error::Repeated("I".to_string())));
+    assert_eq!(verify(&image, &tiles, true, &p), Ok(()));
+}
+
+#[test]
+fn tikz_output() {
+    let f = File::open("tiles/tromino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect3x1").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let solutions = linkage.solve(true);
+    let cover = linkage.cover(solutions.front().unwrap(), &image);
+    let output = tikz::show(&cover, &tiles, 10, true);
+    assert!(output.starts_with("\\begin{tikzpicture}[x=10pt, y=-10pt,"));
+    assert!(output.ends_with("\\end{tikzpicture}\n"));
+    assert!(output.contains("\\definecolor{class0}{RGB}{"));
+    assert!(output.contains("\\draw (3, 1) -- (0, 1) -- (0, 0) -- (3, 0) -- cycle;\n"));
+    assert!(output.contains("\\node at (1.5, 0.5) {I};\n"));
+
+    // every class of the pentominoes drawn separately
+    let f = File::open("tiles/pentomino").unwrap();
+    let tiles = Tiles::load(BufReader::new(f));
+    let output = tikz::show_tiles(&tiles, 10);
+    assert_eq!(output.matches("\\fill").count(), 12);
+    assert_eq!(output.matches("\\node").count(), 12);
+}
+
+#[test]
+fn html_output() {
+    let f = File::open("tiles/domino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect3x2").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, true);
+    let covers = linkage
+        .solve(true)
+        .iter()
+        .map(|s| linkage.cover(s, &image))
+        .collect::<Vec<_>>();
+    let output = html::show(&covers, &tiles, 3, 10);
+    assert!(output.starts_with("<!DOCTYPE html>"));
+    assert!(output.contains("<title>3 solutions</title>"));
+    assert_eq!(output.matches("<figure id=\"solution-").count(), 3);
+    // the legend, two renditions and three solutions
+    assert_eq!(output.matches("<svg ").count(), 6);
+    // three vertical dominoes and two horizontal ones twice
+    assert!(output.contains("<br>5</td>"));
+    assert!(output.contains("<br>4</td>"));
+}
+
+#[test]
+fn ascii_output() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/chess").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let solutions = linkage.solve(false);
+    let cover = linkage.cover(solutions.front().unwrap(), &image);
+    assert_eq!(
+        ascii::show(&cover, &tiles),
+        r#"+-+-+-+-+-+-+-+-+
+|L L|X|U U|V V V|
++ +-+ +-+ + +-+-+
+|L|X X X|U|V|Z Z|
++ +-+ +-+ + + +-+
+|L|F|X|U U|V|Z|N|
++ + +-+-+-+-+ + +
+|L|F F|   |Z Z|N|
++-+ +-+   +-+-+ +
+|F F|Y|   |W|N N|
++-+-+ +-+-+ + +-+
+|Y Y Y Y|W W|N|T|
++-+-+-+-+ +-+-+ +
+|P P P|W W|T T T|
++   +-+-+-+-+-+ +
+|P P|I I I I I|T|
++-+-+-+-+-+-+-+-+
+"#
+    );
+    let solution = solutions.front().unwrap();
+    assert_eq!(linkage.show_solution(solution, &image, &tiles), ascii::show(&cover, &tiles));
+}
+
+#[test]
+fn generate_tiles() {
+    for &(symmetry, counts) in [
+        (Symmetry::Free, [1, 1, 2, 5, 12, 35, 108]),
+        (Symmetry::OneSided, [1, 1, 2, 7, 18, 60, 196]),
+        (Symmetry::Fixed, [1, 2, 6, 19, 63, 216, 760]),
+    ]
+    .iter()
+    {
+        for (size, &count) in counts.iter().enumerate() {
+            assert_eq!(shapes::polyominoes(size + 1, symmetry).len(), count);
+        }
+    }
+
+    // the generated pentominoes solve the same puzzles as the ones loaded, after saving
+    let mut saved = Vec::new();
+    Tiles::generate(5, Symmetry::Free).save(&mut saved).unwrap();
+    let tiles = Tiles::load(&saved[..]);
+    assert_eq!(tiles.kinds_count(), 12);
+    assert_eq!(tiles.iter().count(), 63);
+
+    let i = File::open("images/rect5x4").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    assert_eq!(linkage.count(), 200);
+    assert_eq!(linkage.solve(true).len(), 200);
+}
+
+#[test]
+fn estimate() {
+    let f = File::open("tiles/tromino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let i = File::open("images/rect3x1").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load(i, 'x');
+
+    // the only path of the search tree leads to the only solution
+    let estimate = Linkage::build(&image, &tiles, false).estimate(10, 42);
+    assert_eq!(estimate.nodes, 2.0);
+    assert_eq!(estimate.solutions, 1.0);
+
+    let f = File::open("tiles/pentomino").unwrap();
+    let tiles = Tiles::load(BufReader::new(f));
+    let i = File::open("images/rect5x4").unwrap();
+    let image = Image::load(BufReader::new(i), 'x');
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let estimate = linkage.estimate(5000, 1);
+    assert!(180.0 < estimate.solutions && estimate.solutions < 220.0);
+    // the estimation leaves the linkage as it was
+    assert_eq!(linkage.count(), 200);
+}
+
+#[test]
+fn batch() {
+    let f = File::open("tiles/pentomino").unwrap();
+    let f = BufReader::new(f);
+    let tiles = Tiles::load(f);
+
+    let mut input = std::fs::read_to_string("images/rect5x4").unwrap();
+    input += "\n\n\nxxxxx\n\nxxx\nxxx\n";
+    let images = Image::load_all(input.as_bytes(), 'x');
+    let counts = images
+        .iter()
+        .map(|image| Linkage::build(image, &tiles, false).count())
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![200, 1, 0]);
+
+    // every image keeps the labels of its pixels
+    let images = Image::load_all_labelled(&b"ab\n\n\nba\n"[..], &['a', 'b']);
+    assert_eq!(images.len(), 2);
+    assert_eq!((images[0].label(0), images[1].label(0)), ('a', 'b'));
+}
+
+#[test]
+fn generated_images() {
+    for &(file, ref generated) in [
+        ("images/rect5x4", Image::rectangle(5, 4)),
+        ("images/rect20x3", Image::rectangle(20, 3)),
+        ("images/chess", Image::with_hole(8, 8, 2, 2)),
+    ]
+    .iter()
+    {
+        let i = File::open(file).unwrap();
+        let i = BufReader::new(i);
+        let image = Image::load(i, 'x');
+        assert_eq!(generated.width(), image.width());
+        assert_eq!(generated.height(), image.height());
+        assert!(generated.iter().eq(image.iter()));
+    }
+
+    let diamond = Image::diamond(2);
+    assert_eq!((diamond.width(), diamond.height(), diamond.pointcount()), (5, 5, 13));
+    assert_eq!(diamond.get_point_id(0, 2), Some(0));
+    assert_eq!(diamond.get_point_id(1, 0), None);
+    let staircase = Image::staircase(4);
+    assert_eq!((staircase.width(), staircase.height(), staircase.pointcount()), (4, 4, 10));
+    assert_eq!(staircase.get_point_id(0, 1), None);
+    // the pixels out of 255 x 255 are left out
+    let wide = Image::rectangle(300, 2);
+    assert_eq!((wide.width(), wide.height(), wide.pointcount()), (255, 2, 510));
+
+    // the X pentomino is the only one covering the smallest diamond
+    let f = File::open("tiles/pentomino").unwrap();
+    let tiles = Tiles::load(BufReader::new(f));
+    assert_eq!(Linkage::build(&Image::diamond(1), &tiles, false).count(), 1);
+}
+
+#[test]
+fn builtin_tiles() {
+    let tiles = Tiles::builtin("pentomino").unwrap();
+    assert_eq!(tiles.kinds_count(), 12);
+    assert_eq!(tiles.iter().count(), 63);
+    assert_eq!(Linkage::build(&Image::rectangle(5, 4), &tiles, false).count(), 200);
+
+    // the built-in sets are the ones in tiles/
+    for name in Tiles::builtin_names() {
+        let f = File::open(format!("tiles/{}", name)).unwrap();
+        let loaded = Tiles::load(BufReader::new(f));
+        let builtin = Tiles::builtin(name).unwrap();
+        assert_eq!(builtin.kinds_count(), loaded.kinds_count());
+        assert_eq!(builtin.iter().count(), loaded.iter().count());
+    }
+    assert!(Tiles::builtin("nope").is_none());
+
+    // the built-in polyominoes are the complete sets
+    for &(name, size, symmetry) in [
+        ("monomino", 1, Symmetry::Free),
+        ("domino", 2, Symmetry::Free),
+        ("tromino", 3, Symmetry::Free),
+        ("tetromino", 4, Symmetry::Free),
+        ("tetromino_one_sided", 4, Symmetry::OneSided),
+        ("pentomino", 5, Symmetry::Free),
+        ("hexomino", 6, Symmetry::Free),
+    ]
+    .iter()
+    {
+        let builtin = Tiles::builtin(name).unwrap();
+        let generated = Tiles::generate(size, symmetry);
+        assert_eq!(builtin.kinds_count(), generated.kinds_count());
+        assert_eq!(builtin.iter().count(), generated.iter().count());
+    }
+}
+
+#[test]
+fn tile_pictures() {
+    let f = File::open("tiles/pentomino_art").unwrap();
+    let tiles = Tiles::load(BufReader::new(f));
+    assert_eq!(tiles.kinds_count(), 12);
+    assert_eq!(tiles.iter().count(), 63);
+    assert_eq!(Linkage::build(&Image::rectangle(5, 4), &tiles, false).count(), 200);
+
+    // without the symmetry, the tile is used just as drawn, mixed with the coordinates
+    let tiles = Tiles::load(&b"[L]\n#.\n##\n\nL 1 0\n[T] one-sided\n###\n.#.\n"[..]);
+    assert_eq!(tiles.kinds_count(), 2);
+    assert_eq!(tiles.iter().count(), 6);
+    let l = tiles.iter().next().unwrap();
+    assert_eq!(l.points, vec![Point::new(1, 0), Point::new(1, 1)]);
+
+    // a tile has at most 255 pixels
+    let large = |side: usize| format!("[Q]\n{}", format!("{}\n", "#".repeat(side)).repeat(side));
+    assert_eq!(Tiles::load(large(15).as_bytes()).iter().count(), 1);
+    assert_eq!(Tiles::load(large(16).as_bytes()).iter().count(), 0);
+}
+
+#[test]
+fn class_names() {
+    let tiles = Tiles::load(&b"P5 1 0 0 1 1 1 0 2\nPx 0 1\nI3 0 1 0 2\n[skew] one-sided\n.##\n##.\n"[..]);
+    let names = (0..tiles.kinds_count()).map(|k| tiles.name(k)).collect::<Vec<_>>();
+    assert_eq!(names, ["P5", "Px", "I3", "skew"]);
+    let glyphs = (0..tiles.kinds_count()).map(|k| tiles.glyph(k)).collect::<String>();
+    assert_eq!(glyphs, "PAIs");
+    assert_eq!(tiles.kind("skew"), Some(3));
+    assert_eq!(tiles.kind("s"), None);
+    assert_eq!(tiles.kind_of_glyph('A'), Some(1));
+
+    // the grids of letters are drawn by the glyphs, JSON keeps the names
+    let image = Image::rectangle(7, 2);
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let solutions = linkage.solve(true);
+    assert_eq!(solutions.len(), 1);
+    let placements = solutions
+        .iter()
+        .map(|s| linkage.placements(s, &image))
+        .collect::<Vec<_>>();
+    for placements in placements.iter() {
+        let cover = Cover::from_placements(image.width(), image.height(), placements);
+        let grid = letters::show(&cover, &tiles);
+        assert!(grid.chars().all(|c| "PAIs\n".contains(c)));
+        assert_eq!(letters::parse(grid.as_bytes(), &tiles).unwrap().len(), 4);
+    }
+    let output = json::show(&placements, &tiles, solutions.len(), Duration::from_millis(1), true);
+    assert!(output.contains(r#""class": "skew""#));
+    assert_eq!(json::parse(output.as_bytes(), &tiles).unwrap(), placements);
+}
+
+#[test]
+fn bitmaps() {
+    let i = File::open("images/chess").unwrap();
+    let i = BufReader::new(i);
+    let chess = Image::load(i, 'x');
+
+    for &(file, invert) in [
+        ("bitmaps/chess.pbm", false),
+        ("bitmaps/chess_inverted.pgm", true),
+        ("bitmaps/chess.png", false),
+    ]
+    .iter()
+    {
+        let data = std::fs::read(file).unwrap();
+        assert!(Bitmap::recognize(&data));
+        let bitmap = Bitmap::read(&data).unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (8, 8));
+        let image = Image::from_bitmap(&bitmap, 128, invert);
+        assert_eq!(image.width(), chess.width());
+        assert_eq!(image.height(), chess.height());
+        assert!(image.iter().eq(chess.iter()));
+    }
+    assert!(!Bitmap::recognize(b"xxx\nxxx\n"));
+
+    // a palette of 4-bit colors, compressed with dynamic Huffman codes
+    let bitmap = Bitmap::read(&std::fs::read("png/pentomino.png").unwrap()).unwrap();
+    assert_eq!((bitmap.width(), bitmap.height()), (449, 231));
+    assert_eq!(bitmap.get(0, 0), 255);
+
+    // the bitmaps written by the program are read back, the grid lines are dark
+    let image = Image::rectangle(3, 1);
+    let tiles = Tiles::load(&b"I 0 1 0 2"[..]);
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    let solution = linkage.solve(false).pop_front().unwrap();
+    let cover = linkage.cover(&solution, &image);
+    let bitmap = Bitmap::read(&png::show(&cover, 10)).unwrap();
+    assert_eq!((bitmap.width(), bitmap.height()), (31, 11));
+    assert!(bitmap.get(0, 0) < 128 && bitmap.get(5, 5) >= 128);
+
+    assert!(Bitmap::read(b"P1 2 2 0 1 1").is_err());
+    let png = std::fs::read("bitmaps/chess.png").unwrap();
+    assert!(Bitmap::read(&png[..png.len() - 20]).is_err());
+    let truncated = Bitmap::read(&png[..60]).err().unwrap();
+    assert_eq!(truncated.to_string(), "unexpected end of a PNG picture");
+}
+
+// A PNG picture of 2 x 2 gray pixels (or of the given header) with the given zlib data
+fn png_picture(header: Option<[u8; 13]>, zlib: &[u8]) -> Vec<u8> {
+    let crc = |data: &[u8]| {
+        !data.iter().fold(!0u32, |c, &b| {
+            (0..8).fold(c ^ b as u32, |c, _| if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 })
+        })
+    };
+    let header = header.unwrap_or([0, 0, 0, 2, 0, 0, 0, 2, 8, 0, 0, 0, 0]);
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    for &(kind, data) in [(&b"IHDR"[..], &header[..]), (b"IDAT", zlib), (b"IEND", &[])].iter() {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let chunk = [kind, data].concat();
+        png.extend_from_slice(&chunk);
+        png.extend_from_slice(&crc(&chunk).to_be_bytes());
+    }
+    png
+}
+
+#[test]
+fn malformed_bitmaps() {
+    // stored blocks of 6 bytes, the scanlines of 2 x 2 pixels, and of 100 bytes
+    let stored = |len: u16| {
+        let mut zlib = vec![0x78, 0x01, 0x01];
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend((0..len).map(|_| 0));
+        zlib.extend_from_slice(&(len as u32 * 65536 + 1).to_be_bytes());
+        zlib
+    };
+    let bitmap = Bitmap::read(&png_picture(None, &stored(6))).unwrap();
+    assert_eq!((bitmap.width(), bitmap.height(), bitmap.get(1, 1)), (2, 2, 0));
+    assert!(Bitmap::read(&png_picture(None, &stored(100))).is_err());
+
+    // no pixels, or more of them than the data can hold
+    for &data in [
+        &b"P4 0 3\n"[..],
+        b"P1 3 0\n",
+        b"P5 0 3 255\n",
+        b"P5 3000000 3000000 255\n",
+        b"P6 100 100 255\n\0\0\0",
+        b"P2 99999999999999999999999 2 255\n",
+    ]
+    .iter()
+    {
+        assert!(Bitmap::read(data).is_err());
+    }
+    let zero = [0, 0, 0, 0, 0, 0, 0, 3, 8, 0, 0, 0, 0];
+    assert!(Bitmap::read(&png_picture(Some(zero), &stored(3))).is_err());
+    let huge = [0, 0x2d, 0xc6, 0xc0, 0, 0x2d, 0xc6, 0xc0, 8, 0, 0, 0, 0];
+    assert!(Bitmap::read(&png_picture(Some(huge), &stored(6))).is_err());
+
+    // corrupt deflate streams: an invalid block type, a distance too far back,
+    // a block cut short, a wrong checksum and a stream inflating past the pixels
+    for zlib in [
+        vec![0x78, 0x01, 0x07, 0, 0, 0, 0],
+        vec![0x78, 0x01, 0x03, 0x02, 0x02, 0, 0, 0, 0],
+        vec![0x78, 0x01, 0x01, 0x06, 0x00, 0xf9, 0xff, 0, 0],
+        vec![0x78, 0x01, 0x63, 0x00, 0x02, 0x00, 0, 0, 0, 0],
+        [&[0x78, 0x01, 0x63, 0x18][..], &[0x05; 40], &[0, 0, 0, 0]].concat(),
+    ]
+    .iter()
+    {
+        assert!(Bitmap::read(&png_picture(None, zlib)).is_err());
+    }
+}
+
+#[test]
+fn labels() {
+    let image = Image::load_labelled(&b"aaaaa\nxxxxx\nxxxxx\nxxxxx\n"[..], &['a', 'x']);
+    assert_eq!(image.pointcount(), 20);
+    assert_eq!((image.label(0), image.label(4), image.label(5)), ('a', 'a', 'x'));
+    assert_eq!(Image::load(&b"aaaaa\nxxxxx\n"[..], 'x').pointcount(), 5);
+    assert_eq!(Image::rectangle(2, 2).label(0), polyomino::image::FILLED);
+
+    // the top row covered only by the I pentomino
+    let tiles = Tiles::builtin("pentomino").unwrap();
+    let i = tiles.kind("I").unwrap();
+    let mut linkage = Linkage::build_constrained(&image, &tiles, false, |tile, ids| {
+        ids.iter().all(|&id| image.label(id) != 'a' || tile.kind == i)
+    });
+    assert_eq!(linkage.count(), 28);
+    for solution in linkage.solve(true).iter() {
+        let placements = linkage.placements(solution, &image);
+        let top = placements.iter().find(|p| p.class == i).unwrap();
+        assert!(top.cells.iter().all(|p| p.x == 0));
+    }
+}
+
+#[test]
+fn colors() {
+    let f = File::open("tiles/pentomino_checkered").unwrap();
+    let tiles = Tiles::load(BufReader::new(f));
+    assert_eq!(tiles.kinds_count(), 12);
+
+    let i = File::open("boards/checkered10x6").unwrap();
+    let i = BufReader::new(i);
+    let image = Image::load_labelled(i, &['b', 'w']);
+    // the colors swapped, the solutions mirrored
+    let swapped = Image::load_labelled(
+        &b"wbwbwbwbwb\nbwbwbwbwbw\nwbwbwbwbwb\nbwbwbwbwbw\nwbwbwbwbwb\nbwbwbwbwbw\n"[..],
+        &['b', 'w'],
+    );
+
+    let mut linkage = Linkage::build(&image, &tiles, false);
+    assert_eq!(linkage.count(), 54);
+    assert_eq!(Linkage::build(&swapped, &tiles, false).count(), 54);
+    assert_eq!(Linkage::build(&Image::rectangle(10, 6), &tiles, false).count(), 0);
+    for solution in linkage.solve(true).iter() {
+        let placements = linkage.placements(solution, &image);
+        assert_eq!(verify(&image, &tiles, false, &placements), Ok(()));
+        assert!(matches!(
+            verify(&swapped, &tiles, false, &placements),
+            Err(verify::Error::WrongColors(_))
+        ));
+    }
+
+    // the colors are saved with the coordinates
+    let mut saved = Vec::new();
+    tiles.save(&mut saved).unwrap();
+    let loaded = Tiles::load(&saved[..]);
+    assert_eq!(loaded.iter().count(), tiles.iter().count());
+    assert!(loaded.iter().zip(tiles.iter()).all(|(a, b)| a.colors == b.colors));
+    assert_eq!(Linkage::build(&image, &loaded, false).count(), 54);
+
+    // a pixel without a color covers any pixel, the lists of colors of wrong lengths are skipped
+    let tiles = Tiles::load(&b"D :b# 0 1\nD :bw 0 1 0 2\n"[..]);
+    assert_eq!(tiles.iter().count(), 1);
+    let image = Image::load_labelled(&b"bwbb\n"[..], &['b', 'w']);
+    assert_eq!(Linkage::build(&image, &tiles, true).count(), 1);
+
+    // only in a colored picture the other characters than `#` are colored pixels
+    let tiles = Tiles::load(&b"[D]\n#o-\n\n[E] colored\nb#.w\n"[..]);
+    let mut tiles = tiles.iter();
+    assert_eq!(tiles.next().unwrap().colors, vec![]);
+    assert_eq!(tiles.next().unwrap().colors, vec![Some('b'), None, Some('w')]);
+}